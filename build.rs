@@ -1,34 +1,61 @@
 use std::env;
+use std::fs;
+
+const DEFAULT_RISCV_GNU_TOOLCHAIN: &str = "/opt/riscv";
+
+fn configure_riscv(build: &mut cc::Build, compiler: &str) {
+    let riscv_gnu_toolchain_path =
+        env::var("RISCV_GNU_TOOLCHAIN").unwrap_or_else(|_| DEFAULT_RISCV_GNU_TOOLCHAIN.into());
+
+    build
+        .compiler(compiler)
+        .no_default_flags(true)
+        .flag(format!(
+            "--sysroot={riscv_gnu_toolchain_path}/riscv32-unknown-elf"
+        ))
+        .flag(format!("--gcc-toolchain={riscv_gnu_toolchain_path}"))
+        .flag("--target=riscv32-unknown-none-elf")
+        .flag("-march=rv32im")
+        .flag("-mabi=ilp32")
+        .flag("-mcmodel=medany")
+        .flag("-Os")
+        .flag("-fdata-sections")
+        .flag("-ffunction-sections")
+        .flag("-flto")
+        .target("riscv32im-unknown-none-elf");
+}
 
 fn main() {
+    // The sp1-sha256 hooks come from src/native/patches/sp1-sha256.patch. Without them the
+    // precompiles would silently go unused, so a re-vendored tree missing them is an error
+    if env::var("CARGO_FEATURE_SP1_SHA256").is_ok() {
+        let sha256 = fs::read_to_string("src/native/vendor/bitcoin/src/crypto/sha256.cpp")
+            .expect("failed to read the vendored crypto/sha256.cpp");
+        assert!(
+            sha256.contains("TransformType Transform = sha256_sp1::Transform;"),
+            "sp1-sha256 requires src/native/patches/sp1-sha256.patch applied to the vendored Bitcoin Core"
+        );
+    }
+
     let mut base_config = cc::Build::new();
-    const DEFAULT_RISCV_GNU_TOOLCHAIN: &str = "/opt/riscv";
+    let mut secp256k1_config = cc::Build::new();
     if env::var("CARGO_CFG_TARGET_ARCH").unwrap() == "riscv32" {
         println!("cargo:rerun-if-env-changed=RISCV_GNU_TOOLCHAIN");
 
-        let riscv_gnu_toolchain_path =
-            env::var("RISCV_GNU_TOOLCHAIN").unwrap_or_else(|_| DEFAULT_RISCV_GNU_TOOLCHAIN.into());
-
+        configure_riscv(&mut base_config, "clang++");
         base_config
-            .compiler("clang++")
-            .no_default_flags(true)
-            .flag(&format!(
-                "--sysroot={riscv_gnu_toolchain_path}/riscv32-unknown-elf"
-            ))
-            .flag(&format!("--gcc-toolchain={riscv_gnu_toolchain_path}"))
-            .flag("--target=riscv32-unknown-none-elf")
-            .flag("-march=rv32im")
-            .flag("-mabi=ilp32")
-            .flag("-mcmodel=medany")
-            .flag("-Os")
-            .flag("-fdata-sections")
-            .flag("-ffunction-sections")
-            .flag("-flto")
             .flag("-fno-threadsafe-statics")
             .flag("-D_POSIX_TIMERS")
             .flag("-include")
-            .flag("sys/time.h")
-            .target("riscv32im-unknown-none-elf");
+            .flag("sys/time.h");
+        configure_riscv(&mut secp256k1_config, "clang");
+
+        // Routes CSHA256's transform to the SP1 precompiles, see src/native/sha256_sp1.cpp
+        if env::var("CARGO_FEATURE_SP1_SHA256").is_ok() {
+            base_config
+                .define("ENABLE_SP1_SHA256", None)
+                .file("src/native/sha256_sp1.cpp");
+        }
     }
 
     base_config
@@ -38,7 +65,49 @@ fn main() {
         .flag("-fvisibility=default")
         .include("src/native/vendor/bitcoin/src")
         .include("src/native/vendor/bitcoin/src/univalue/include")
+        .include("src/native/vendor/bitcoin/src/secp256k1/include");
+
+    // Bitcoin Core's hardware accelerated SHA-256 implementations. Each one is compiled with its
+    // own instruction set flags, so only SHA256AutoDetect decides at runtime whether it is used
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+    let sha256_variants: &[(&str, &str, &[&str])] = match target_arch.as_str() {
+        "x86_64" => &[
+            ("sha256_sse41.cpp", "ENABLE_SSE41", &["-msse4.1"]),
+            ("sha256_avx2.cpp", "ENABLE_AVX2", &["-mavx", "-mavx2"]),
+            (
+                "sha256_x86_shani.cpp",
+                "ENABLE_X86_SHANI",
+                &["-msse4", "-msha"],
+            ),
+        ],
+        "aarch64" => &[(
+            "sha256_arm_shani.cpp",
+            "ENABLE_ARM_SHANI",
+            &["-march=armv8-a+crypto"],
+        )],
+        _ => &[],
+    };
+    for (file, define, flags) in sha256_variants {
+        let mut variant_config = base_config.clone();
+        variant_config
+            .define(define, None)
+            .file(format!("src/native/vendor/bitcoin/src/crypto/{file}"));
+        for flag in *flags {
+            variant_config.flag(flag);
+        }
+        base_config
+            .define(define, None)
+            .objects(variant_config.compile_intermediates());
+    }
+    if target_arch == "x86_64" {
+        // Inline assembly that only needs the baseline x86_64 instruction set
+        base_config.file("src/native/vendor/bitcoin/src/crypto/sha256_sse4.cpp");
+    }
+
+    base_config
         .file("src/native/vendor/bitcoin/src/crypto/sha256.cpp")
+        .file("src/native/vendor/bitcoin/src/crypto/ripemd160.cpp")
+        .file("src/native/vendor/bitcoin/src/crypto/sha1.cpp")
         .file("src/native/vendor/bitcoin/src/pow.cpp")
         .file("src/native/vendor/bitcoin/src/uint256.cpp")
         .file("src/native/vendor/bitcoin/src/arith_uint256.cpp")
@@ -50,12 +119,14 @@ fn main() {
         .file("src/native/vendor/bitcoin/src/kernel/chainparams.cpp")
         .file("src/native/vendor/bitcoin/src/chainparamsbase.cpp")
         .file("src/native/vendor/bitcoin/src/consensus/merkle.cpp")
+        .file("src/native/vendor/bitcoin/src/consensus/tx_check.cpp")
         .file("src/native/vendor/bitcoin/src/util/chaintype.cpp")
         .file("src/native/vendor/bitcoin/src/util/strencodings.cpp")
         .file("src/native/vendor/bitcoin/src/util/string.cpp")
         .file("src/native/vendor/bitcoin/src/util/time.cpp")
         .file("src/native/vendor/bitcoin/src/deploymentinfo.cpp")
         .file("src/native/vendor/bitcoin/src/hash.cpp")
+        .file("src/native/vendor/bitcoin/src/pubkey.cpp")
         .file("src/native/vendor/bitcoin/src/primitives/transaction.cpp")
         .file("src/native/vendor/bitcoin/src/script/interpreter.cpp")
         .file("src/native/vendor/bitcoin/src/script/script.cpp")
@@ -70,6 +141,25 @@ fn main() {
         .file("src/native/bitcoin_core_wrapper.cpp")
         .compile("bitcoin_core_lib");
 
+    // Same configuration as Bitcoin Core's own libsecp256k1 build. The recovery module is only
+    // needed because pubkey.cpp references it. Verification uses secp256k1_context_static, so
+    // no context has to be created at runtime
+    secp256k1_config
+        .include("src/native/vendor/bitcoin/src/secp256k1/include")
+        .define("ENABLE_MODULE_EXTRAKEYS", "1")
+        .define("ENABLE_MODULE_SCHNORRSIG", "1")
+        .define("ENABLE_MODULE_ELLSWIFT", "1")
+        .define("ENABLE_MODULE_RECOVERY", "1")
+        .define("ECMULT_WINDOW_SIZE", "15")
+        .define("COMB_BLOCKS", "43")
+        .define("COMB_TEETH", "6")
+        .define("SECP256K1_STATIC", None)
+        .flag("-Wno-unused-function")
+        .file("src/native/vendor/bitcoin/src/secp256k1/src/secp256k1.c")
+        .file("src/native/vendor/bitcoin/src/secp256k1/src/precomputed_ecmult.c")
+        .file("src/native/vendor/bitcoin/src/secp256k1/src/precomputed_ecmult_gen.c")
+        .compile("secp256k1");
+
     if env::var("CARGO_CFG_TARGET_OS").unwrap() == "macos" {
         println!("cargo:rustc-link-lib=c++");
     } else if env::var("CARGO_CFG_TARGET_OS").unwrap() == "linux" {
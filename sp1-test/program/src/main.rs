@@ -22,13 +22,15 @@ pub fn main() {
 
     // Test check_proof_of_work
     assert!(bitcoin_core_rs::check_proof_of_work(
-        &serialized_header_array
+        &serialized_header_array,
+        bitcoin_core_rs::Network::Mainnet
     ));
     println!("Proof of work verified");
 
     // Test get_retarget_height (using a sample height)
     let height = 40320u32;
-    let retarget_height = bitcoin_core_rs::get_retarget_height(height);
+    let retarget_height =
+        bitcoin_core_rs::get_retarget_height(height, bitcoin_core_rs::Network::Mainnet);
     println!("Retarget height computed: {}", retarget_height);
 
     // Test get_block_proof
@@ -1,5 +1,14 @@
-use sp1_helper::build_program_with_args;
+use sp1_helper::{build_program_with_args, BuildArgs};
 
 fn main() {
-    build_program_with_args("../program", Default::default())
+    build_program_with_args("../program", Default::default());
+    // The same program hashing through the SHA-256 precompiles, to compare cycle counts
+    build_program_with_args(
+        "../program",
+        BuildArgs {
+            features: vec!["sha256-precompile".to_string()],
+            elf_name: "riscv32im-succinct-zkvm-elf-sha256-precompile".to_string(),
+            ..Default::default()
+        },
+    );
 }
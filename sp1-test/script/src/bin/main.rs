@@ -16,6 +16,10 @@ use sp1_sdk::{ProverClient, SP1Stdin};
 /// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
 pub const PROGRAM_ELF: &[u8] = include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf");
 
+/// The same program with Bitcoin Core's SHA-256 routed to the zkVM precompiles.
+pub const PROGRAM_PRECOMPILE_ELF: &[u8] =
+    include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf-sha256-precompile");
+
 /// The arguments for the command.
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -56,11 +60,27 @@ fn main() {
     if args.execute {
         println!("Executing program...");
         // Execute the program
-        let (output, report) = client.execute(PROGRAM_ELF, stdin).run().unwrap();
+        let (output, report) = client.execute(PROGRAM_ELF, stdin.clone()).run().unwrap();
         println!("Program executed successfully, header valid under PoW...");
 
         // Record the number of cycles executed.
-        println!("Number of cycles: {}", report.total_instruction_count());
+        let cycles = report.total_instruction_count();
+        println!("Number of cycles: {}", cycles);
+
+        println!("Executing program with the SHA-256 precompiles...");
+        let (precompile_output, precompile_report) =
+            client.execute(PROGRAM_PRECOMPILE_ELF, stdin).run().unwrap();
+        assert_eq!(
+            precompile_output.as_slice(),
+            output.as_slice(),
+            "precompiles changed the program output"
+        );
+        let precompile_cycles = precompile_report.total_instruction_count();
+        println!(
+            "Number of cycles with precompiles: {} ({:+.1}%)",
+            precompile_cycles,
+            (precompile_cycles as f64 / cycles as f64 - 1.0) * 100.0
+        );
     } else {
         // Setup the program for proving.
         let (pk, vk) = client.setup(PROGRAM_ELF);
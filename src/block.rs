@@ -0,0 +1,649 @@
+use crate::error::BlockValidationError;
+use crate::ffi;
+use crate::network::Network;
+use crate::transaction::Transaction;
+
+/// Runs the context-free checks of Bitcoin Core's `CheckBlock` on a serialized block.
+///
+/// This covers the proof of work against the network's limit, the merkle root and its
+/// mutation check, the size and weight limits, coinbase position, `CheckTransaction` on
+/// every transaction and the legacy sigop limit. Like header validation, the signet block
+/// solution is not checked. Witness data is checked by [`check_witness_commitment`].
+pub fn check_block(block: &[u8], network: Network) -> Result<(), BlockValidationError> {
+    let code = unsafe { ffi::check_block(block.as_ptr(), block.len() as u32, network) };
+    match BlockValidationError::from_code(code) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Index of the output of `coinbase` holding the segwit witness commitment, see
+/// `GetWitnessCommitmentIndex`. If several outputs match, the last one is the commitment.
+pub fn get_witness_commitment_index(coinbase: &Transaction) -> Option<usize> {
+    let bytes = coinbase.as_bytes();
+    let index = unsafe { ffi::get_witness_commitment_index(bytes.as_ptr(), bytes.len() as u32) };
+    usize::try_from(index).ok()
+}
+
+/// Computes the witness commitment a segwit block's coinbase must contain: the double
+/// SHA256 of [`crate::block_witness_merkle_root`] and the witness reserved value, which
+/// has to be the coinbase's only witness item.
+pub fn compute_witness_commitment(block: &[u8]) -> Result<[u8; 32], BlockValidationError> {
+    let mut commitment = [0u8; 32];
+    let code = unsafe {
+        ffi::compute_witness_commitment(block.as_ptr(), block.len() as u32, commitment.as_mut_ptr())
+    };
+    match BlockValidationError::from_code(code) {
+        Some(error) => Err(error),
+        None => Ok(commitment),
+    }
+}
+
+/// Runs the witness checks of Bitcoin Core's `ContextualCheckBlock` with segwit active.
+///
+/// If the coinbase has a witness commitment it must match [`compute_witness_commitment`],
+/// otherwise no transaction may carry witness data. The block weight limit is checked
+/// last, as only then is the coinbase witness known not to be padded.
+pub fn check_witness_commitment(block: &[u8]) -> Result<(), BlockValidationError> {
+    let code = unsafe { ffi::check_witness_commitment(block.as_ptr(), block.len() as u32) };
+    match BlockValidationError::from_code(code) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Reads the block height BIP34 requires at the start of a coinbase scriptSig.
+///
+/// Returns `None` if `coinbase` isn't a coinbase or its scriptSig doesn't start with a
+/// height encoded like `CScript() << nHeight`. Coinbases of blocks below the network's
+/// BIP34 height may not contain one, or even contain a wrong one.
+pub fn coinbase_height(coinbase: &Transaction) -> Option<u32> {
+    let bytes = coinbase.as_bytes();
+    let mut height = 0u32;
+    let found = unsafe { ffi::coinbase_height(bytes.as_ptr(), bytes.len() as u32, &mut height) };
+    found.then_some(height)
+}
+
+/// Runs the BIP34 check of Bitcoin Core's `ContextualCheckBlock` on the coinbase of the
+/// block at `height`: once the network's BIP34 height is reached, its scriptSig must
+/// start with `height`. This binds a block to its height through the merkle root.
+pub fn check_coinbase_height(
+    coinbase: &Transaction,
+    height: u32,
+    network: Network,
+) -> Result<(), BlockValidationError> {
+    let bytes = coinbase.as_bytes();
+    let code =
+        unsafe { ffi::check_coinbase_height(bytes.as_ptr(), bytes.len() as u32, height, network) };
+    match BlockValidationError::from_code(code) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Block subsidy in satoshis at `height`, see `GetBlockSubsidy`. It halves every
+/// `nSubsidyHalvingInterval` blocks and is zero from the 64th halving on.
+pub fn get_block_subsidy(height: u32, network: Network) -> i64 {
+    unsafe { ffi::get_block_subsidy(height, network) }
+}
+
+/// Runs the fee and coinbase value checks of Bitcoin Core's `ConnectBlock`, failing with
+/// `bad-cb-amount` if the coinbase claims more than the subsidy at `height` plus the fees.
+///
+/// `input_values` are the values of the outputs spent by every input of every transaction
+/// after the coinbase, in block order. They are trusted to be the actual spent outputs, as
+/// this doesn't have the UTXO set to look them up in.
+pub fn check_coinbase_value(
+    block: &[u8],
+    input_values: &[i64],
+    height: u32,
+    network: Network,
+) -> Result<(), BlockValidationError> {
+    let code = unsafe {
+        ffi::check_coinbase_value(
+            block.as_ptr(),
+            block.len() as u32,
+            input_values.as_ptr(),
+            input_values.len() as u32,
+            height,
+            network,
+        )
+    };
+    match BlockValidationError::from_code(code) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::BlockHeader;
+    use crate::merkle::compute_merkle_root;
+    use crate::test_utils::mine;
+    use crate::transaction::Transaction;
+    use alloy_primitives::hex;
+
+    const GENESIS_HEADER: [u8; 80] = hex!("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c");
+    const GENESIS_COINBASE: [u8; 204] = hex!("01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000");
+
+    const OP_TRUE: u8 = 0x51;
+    const OP_CHECKSIG: u8 = 0xac;
+
+    fn compact_size(n: usize) -> Vec<u8> {
+        match n {
+            0..=0xfc => vec![n as u8],
+            0xfd..=0xffff => [&[0xfd], &(n as u16).to_le_bytes()[..]].concat(),
+            _ => [&[0xfe], &(n as u32).to_le_bytes()[..]].concat(),
+        }
+    }
+
+    // Serializes a version 1 transaction, in the segwit encoding if any input has a witness
+    fn segwit_transaction(
+        inputs: &[([u8; 32], u32, &[u8])],
+        outputs: &[(i64, &[u8])],
+        witnesses: &[Vec<Vec<u8>>],
+    ) -> Vec<u8> {
+        let has_witness = witnesses.iter().any(|witness| !witness.is_empty());
+        let mut tx = 1u32.to_le_bytes().to_vec();
+        if has_witness {
+            tx.extend([0x00, 0x01]);
+        }
+        tx.extend(compact_size(inputs.len()));
+        for (txid, vout, script_sig) in inputs {
+            tx.extend(txid);
+            tx.extend(vout.to_le_bytes());
+            tx.extend(compact_size(script_sig.len()));
+            tx.extend(*script_sig);
+            tx.extend(u32::MAX.to_le_bytes());
+        }
+        tx.extend(compact_size(outputs.len()));
+        for (value, script_pubkey) in outputs {
+            tx.extend(value.to_le_bytes());
+            tx.extend(compact_size(script_pubkey.len()));
+            tx.extend(*script_pubkey);
+        }
+        if has_witness {
+            for index in 0..inputs.len() {
+                let witness = witnesses.get(index).map(Vec::as_slice).unwrap_or_default();
+                tx.extend(compact_size(witness.len()));
+                for item in witness {
+                    tx.extend(compact_size(item.len()));
+                    tx.extend(item);
+                }
+            }
+        }
+        tx.extend(0u32.to_le_bytes());
+        tx
+    }
+
+    fn transaction(inputs: &[([u8; 32], u32, &[u8])], outputs: &[(i64, &[u8])]) -> Vec<u8> {
+        segwit_transaction(inputs, outputs, &[])
+    }
+
+    fn coinbase(script_sig: &[u8], outputs: &[(i64, &[u8])]) -> Vec<u8> {
+        transaction(&[([0u8; 32], u32::MAX, script_sig)], outputs)
+    }
+
+    fn coinbase_tx(script_sig: &[u8]) -> Transaction {
+        Transaction::from_bytes(&coinbase(script_sig, &[(0, &[OP_TRUE])])).unwrap()
+    }
+
+    fn txid(tx: &[u8]) -> [u8; 32] {
+        Transaction::from_bytes(tx).unwrap().txid()
+    }
+
+    // Mines a regtest block committing to merkle_root
+    fn block_with_root(txs: &[Vec<u8>], merkle_root: [u8; 32]) -> Vec<u8> {
+        let header = mine(BlockHeader {
+            version: 4,
+            prev_blockhash: [0u8; 32],
+            merkle_root,
+            time: 1_700_000_000,
+            bits: 0x207fffff,
+            nonce: 0,
+        });
+        let mut block = header.to_bytes().to_vec();
+        block.extend(compact_size(txs.len()));
+        for tx in txs {
+            block.extend(tx);
+        }
+        block
+    }
+
+    fn block(txs: &[Vec<u8>]) -> Vec<u8> {
+        let txids: Vec<[u8; 32]> = txs.iter().map(|tx| txid(tx)).collect();
+        block_with_root(txs, compute_merkle_root(&txids).0)
+    }
+
+    #[test]
+    fn test_check_block_genesis() {
+        let mut genesis = GENESIS_HEADER.to_vec();
+        genesis.push(1);
+        genesis.extend(GENESIS_COINBASE);
+        assert_eq!(check_block(&genesis, Network::Mainnet), Ok(()));
+
+        let mut high_hash = genesis.clone();
+        high_hash[76] ^= 1;
+        assert_eq!(
+            check_block(&high_hash, Network::Mainnet),
+            Err(BlockValidationError::HighHash)
+        );
+
+        assert_eq!(
+            check_block(&genesis[..genesis.len() - 1], Network::Mainnet),
+            Err(BlockValidationError::Deserialize)
+        );
+        assert_eq!(
+            check_block(&[genesis.as_slice(), &[0]].concat(), Network::Mainnet),
+            Err(BlockValidationError::Deserialize)
+        );
+    }
+
+    #[test]
+    fn test_check_block() {
+        let cb = coinbase(&[0x01, 0x01], &[(50 * 100_000_000, &[OP_TRUE])]);
+        let spend = transaction(&[(txid(&cb), 0, &[])], &[(1_000, &[OP_TRUE])]);
+        let valid = block(&[cb.clone(), spend.clone()]);
+        assert_eq!(check_block(&valid, Network::Regtest), Ok(()));
+
+        let check = |txs: &[Vec<u8>]| check_block(&block(txs), Network::Regtest);
+
+        assert_eq!(
+            check_block(
+                &block_with_root(&[cb.clone(), spend.clone()], txid(&spend)),
+                Network::Regtest
+            ),
+            Err(BlockValidationError::BadMerkleRoot)
+        );
+        // the last two leaves are identical, so the root is that of [cb, spend, other]
+        let other = transaction(&[(txid(&cb), 0, &[])], &[(2_000, &[OP_TRUE])]);
+        assert_eq!(
+            check(&[cb.clone(), spend.clone(), other.clone(), other]),
+            Err(BlockValidationError::DuplicateTransaction)
+        );
+        assert_eq!(
+            check_block(&block_with_root(&[], [0u8; 32]), Network::Regtest),
+            Err(BlockValidationError::BadLength)
+        );
+        assert_eq!(check(&[spend]), Err(BlockValidationError::CoinbaseMissing));
+        let second_cb = coinbase(&[0x01, 0x02], &[(0, &[OP_TRUE])]);
+        assert_eq!(
+            check(&[cb.clone(), second_cb]),
+            Err(BlockValidationError::MultipleCoinbase)
+        );
+        assert_eq!(
+            check(&[coinbase(&[0x01], &[(0, &[OP_TRUE])])]),
+            Err(BlockValidationError::CoinbaseLength)
+        );
+
+        // transaction checks fail the block with the transaction's reject reason
+        let no_outputs = transaction(&[(txid(&cb), 0, &[])], &[]);
+        assert_eq!(
+            check(&[cb.clone(), no_outputs]),
+            Err(BlockValidationError::OutputsEmpty)
+        );
+        let double_spend = transaction(
+            &[(txid(&cb), 0, &[]), (txid(&cb), 0, &[])],
+            &[(1_000, &[OP_TRUE])],
+        );
+        assert_eq!(
+            check(&[cb.clone(), double_spend]),
+            Err(BlockValidationError::DuplicateInputs)
+        );
+        let negative = transaction(&[(txid(&cb), 0, &[])], &[(-1, &[OP_TRUE])]);
+        assert_eq!(
+            check(&[cb.clone(), negative]),
+            Err(BlockValidationError::OutputNegative)
+        );
+        let null_prevout = transaction(
+            &[(txid(&cb), 0, &[]), ([0u8; 32], u32::MAX, &[])],
+            &[(1_000, &[OP_TRUE])],
+        );
+        assert_eq!(
+            check(&[cb.clone(), null_prevout]),
+            Err(BlockValidationError::PrevoutNull)
+        );
+
+        // 20,001 legacy sigops weigh 80,004, over MAX_BLOCK_SIGOPS_COST
+        let sigops = vec![OP_CHECKSIG; 20_001];
+        assert_eq!(
+            check(&[coinbase(&[0x01, 0x01], &[(0, &sigops)])]),
+            Err(BlockValidationError::TooManySigops)
+        );
+        let sigops = vec![OP_CHECKSIG; 20_000];
+        assert_eq!(check(&[coinbase(&[0x01, 0x01], &[(0, &sigops)])]), Ok(()));
+    }
+
+    fn commitment_script(commitment: &[u8; 32]) -> Vec<u8> {
+        [&hex!("6a24aa21a9ed")[..], commitment].concat()
+    }
+
+    #[test]
+    fn test_get_witness_commitment_index() {
+        let tx = |outputs: &[(i64, &[u8])]| {
+            Transaction::from_bytes(&coinbase(&[0x01, 0x01], outputs)).unwrap()
+        };
+        assert_eq!(get_witness_commitment_index(&tx(&[(0, &[OP_TRUE])])), None);
+
+        let first = commitment_script(&[1u8; 32]);
+        let second = commitment_script(&[2u8; 32]);
+        assert_eq!(
+            get_witness_commitment_index(&tx(&[(0, &first), (0, &[OP_TRUE])])),
+            Some(0)
+        );
+        assert_eq!(
+            get_witness_commitment_index(&tx(&[(0, &first), (0, &[OP_TRUE]), (0, &second)])),
+            Some(2)
+        );
+        // the commitment can be followed by extra data, but not be shortened
+        assert_eq!(
+            get_witness_commitment_index(&tx(&[(0, &[first.as_slice(), &[0xff]].concat())])),
+            Some(0)
+        );
+        assert_eq!(
+            get_witness_commitment_index(&tx(&[(0, &first[..37])])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_witness_commitment() {
+        let reserved_value = vec![vec![0u8; 32]];
+        let segwit_coinbase = |commitment: &[u8; 32], witness: &[Vec<u8>]| {
+            segwit_transaction(
+                &[([0u8; 32], u32::MAX, &[0x01, 0x01])],
+                &[(0, &[OP_TRUE]), (0, &commitment_script(commitment))],
+                &[witness.to_vec()],
+            )
+        };
+        let legacy_cb = coinbase(&[0x01, 0x01], &[(0, &[OP_TRUE])]);
+        let spend_witness = |witness: Vec<Vec<u8>>| {
+            segwit_transaction(
+                &[(txid(&legacy_cb), 0, &[])],
+                &[(1_000, &[OP_TRUE])],
+                &[witness],
+            )
+        };
+        let spend = spend_witness(vec![vec![1, 2, 3]]);
+
+        // the coinbase wtxid is taken as zero, so its own commitment doesn't change the result
+        let placeholder = block(&[segwit_coinbase(&[0u8; 32], &reserved_value), spend.clone()]);
+        let commitment = compute_witness_commitment(&placeholder).unwrap();
+        let (witness_root, _) = crate::block_witness_merkle_root(&placeholder).unwrap();
+        assert_eq!(
+            commitment,
+            crate::sha256(&crate::sha256(&[witness_root, [0u8; 32]].concat()))
+        );
+
+        let valid = block(&[segwit_coinbase(&commitment, &reserved_value), spend.clone()]);
+        assert_eq!(check_witness_commitment(&valid), Ok(()));
+        assert_eq!(check_block(&valid, Network::Regtest), Ok(()));
+
+        let wrong_commitment =
+            block(&[segwit_coinbase(&[1u8; 32], &reserved_value), spend.clone()]);
+        assert_eq!(
+            check_witness_commitment(&wrong_commitment),
+            Err(BlockValidationError::WitnessMerkleMatch)
+        );
+        // witness data isn't covered by the merkle root, so changing it breaks only the commitment
+        let other_witness = block(&[
+            segwit_coinbase(&commitment, &reserved_value),
+            spend_witness(vec![vec![4, 5, 6]]),
+        ]);
+        assert_eq!(check_block(&other_witness, Network::Regtest), Ok(()));
+        assert_eq!(
+            check_witness_commitment(&other_witness),
+            Err(BlockValidationError::WitnessMerkleMatch)
+        );
+
+        let no_reserved_value = block(&[segwit_coinbase(&commitment, &[]), spend.clone()]);
+        assert_eq!(
+            check_witness_commitment(&no_reserved_value),
+            Err(BlockValidationError::WitnessNonceSize)
+        );
+        assert_eq!(
+            compute_witness_commitment(&no_reserved_value),
+            Err(BlockValidationError::WitnessNonceSize)
+        );
+
+        // without a commitment, witness data is only allowed if there is none
+        assert_eq!(
+            check_witness_commitment(&block(&[legacy_cb.clone(), spend.clone()])),
+            Err(BlockValidationError::UnexpectedWitness)
+        );
+        assert_eq!(
+            check_witness_commitment(&block(&[legacy_cb.clone(), spend_witness(vec![])])),
+            Ok(())
+        );
+
+        assert_eq!(
+            check_witness_commitment(&block(&[spend])),
+            Err(BlockValidationError::CoinbaseMissing)
+        );
+    }
+
+    #[test]
+    fn test_witness_commitment_weight() {
+        let cb = coinbase(&[0x01, 0x01], &[(0, &[OP_TRUE])]);
+        // a witness item alone is enough to exceed MAX_BLOCK_WEIGHT, even though
+        // the block passes the size limits of check_block
+        let spend = segwit_transaction(
+            &[(txid(&cb), 0, &[])],
+            &[(1_000, &[OP_TRUE])],
+            &[vec![vec![0u8; 4_000_000]]],
+        );
+        let segwit_coinbase = |commitment: &[u8; 32]| {
+            segwit_transaction(
+                &[([0u8; 32], u32::MAX, &[0x01, 0x01])],
+                &[(0, &[OP_TRUE]), (0, &commitment_script(commitment))],
+                &[vec![vec![0u8; 32]]],
+            )
+        };
+        let commitment =
+            compute_witness_commitment(&block(&[segwit_coinbase(&[0u8; 32]), spend.clone()]))
+                .unwrap();
+        let heavy = block(&[segwit_coinbase(&commitment), spend]);
+        assert_eq!(check_block(&heavy, Network::Regtest), Ok(()));
+        assert_eq!(
+            check_witness_commitment(&heavy),
+            Err(BlockValidationError::BadWeight)
+        );
+    }
+
+    #[test]
+    fn test_coinbase_height() {
+        let height = |script_sig: &[u8]| coinbase_height(&coinbase_tx(script_sig));
+        assert_eq!(height(&hex!("00")), Some(0));
+        assert_eq!(height(&hex!("51")), Some(1));
+        assert_eq!(height(&hex!("60")), Some(16));
+        assert_eq!(height(&hex!("0111")), Some(17));
+        assert_eq!(height(&hex!("028000")), Some(128));
+        assert_eq!(height(&hex!("02ff00")), Some(255));
+        // mainnet block 227931, the first with BIP34 enforced, followed by an extra nonce
+        assert_eq!(height(&hex!("035b7a03")), Some(227_931));
+        assert_eq!(height(&hex!("035b7a0304deadbeef")), Some(227_931));
+        assert_eq!(height(&hex!("0340d10c")), Some(840_000));
+        assert_eq!(height(&hex!("05ffffffff00")), Some(u32::MAX));
+        assert_eq!(height(&hex!("050000000001")), None);
+
+        // small heights must use OP_N, and pushes must be minimal
+        assert_eq!(height(&hex!("0105")), None);
+        assert_eq!(height(&hex!("020500")), None);
+        assert_eq!(height(&hex!("0440d10c00")), None);
+        assert_eq!(height(&hex!("4c015b")), None);
+        // negative heights
+        assert_eq!(height(&hex!("4f")), None);
+        assert_eq!(height(&hex!("0181")), None);
+        // not a push
+        assert_eq!(height(&hex!("ac")), None);
+        assert_eq!(height(&[]), None);
+        // truncated push
+        assert_eq!(height(&hex!("035b7a")), None);
+
+        let spend = Transaction::from_bytes(&transaction(
+            &[([1u8; 32], 0, &hex!("035b7a03"))],
+            &[(0, &[OP_TRUE])],
+        ))
+        .unwrap();
+        assert_eq!(coinbase_height(&spend), None);
+    }
+
+    #[test]
+    fn test_check_coinbase_height() {
+        let cb = coinbase_tx(&hex!("035b7a0304deadbeef"));
+        assert_eq!(
+            check_coinbase_height(&cb, 227_931, Network::Mainnet),
+            Ok(())
+        );
+        assert_eq!(
+            check_coinbase_height(&cb, 227_932, Network::Mainnet),
+            Err(BlockValidationError::BadCoinbaseHeight)
+        );
+        // BIP34 is enforced from height 21111 on testnet3
+        assert_eq!(
+            check_coinbase_height(&cb, 21_110, Network::Testnet3),
+            Ok(())
+        );
+        assert_eq!(
+            check_coinbase_height(&cb, 21_111, Network::Testnet3),
+            Err(BlockValidationError::BadCoinbaseHeight)
+        );
+
+        // below BIP34Height, anything goes
+        let pre_bip34 = coinbase_tx(&hex!("04ffff001d0104"));
+        assert_eq!(
+            check_coinbase_height(&pre_bip34, 227_930, Network::Mainnet),
+            Ok(())
+        );
+        assert_eq!(
+            check_coinbase_height(&pre_bip34, 227_931, Network::Mainnet),
+            Err(BlockValidationError::BadCoinbaseHeight)
+        );
+
+        let regtest = coinbase_tx(&hex!("5100"));
+        assert_eq!(check_coinbase_height(&regtest, 1, Network::Regtest), Ok(()));
+        assert_eq!(
+            check_coinbase_height(&regtest, 2, Network::Regtest),
+            Err(BlockValidationError::BadCoinbaseHeight)
+        );
+
+        let spend = Transaction::from_bytes(&transaction(
+            &[([1u8; 32], 0, &hex!("035b7a03"))],
+            &[(0, &[OP_TRUE])],
+        ))
+        .unwrap();
+        assert_eq!(
+            check_coinbase_height(&spend, 227_931, Network::Mainnet),
+            Err(BlockValidationError::CoinbaseMissing)
+        );
+    }
+
+    const COIN: i64 = 100_000_000;
+    const MAX_MONEY: i64 = 21_000_000 * COIN;
+
+    #[test]
+    fn test_get_block_subsidy() {
+        assert_eq!(get_block_subsidy(0, Network::Mainnet), 50 * COIN);
+        assert_eq!(get_block_subsidy(209_999, Network::Mainnet), 50 * COIN);
+        assert_eq!(get_block_subsidy(210_000, Network::Mainnet), 25 * COIN);
+        assert_eq!(get_block_subsidy(840_000, Network::Mainnet), 312_500_000);
+        // the last satoshi of subsidy is paid in the 33rd era
+        assert_eq!(get_block_subsidy(32 * 210_000, Network::Mainnet), 1);
+        assert_eq!(get_block_subsidy(33 * 210_000, Network::Mainnet), 0);
+        assert_eq!(get_block_subsidy(64 * 210_000, Network::Mainnet), 0);
+        assert_eq!(get_block_subsidy(u32::MAX, Network::Mainnet), 0);
+
+        assert_eq!(get_block_subsidy(149, Network::Regtest), 50 * COIN);
+        assert_eq!(get_block_subsidy(150, Network::Regtest), 25 * COIN);
+        // with a 150 block interval, right shifts of 64 and more are reached early
+        assert_eq!(get_block_subsidy(64 * 150 - 1, Network::Regtest), 0);
+        assert_eq!(get_block_subsidy(64 * 150, Network::Regtest), 0);
+
+        // the subsidies of all eras add up to just under 21 million coins
+        let total: i64 = (0..64)
+            .map(|era| get_block_subsidy(era * 210_000, Network::Mainnet) * 210_000)
+            .sum();
+        assert_eq!(total, 2_099_999_997_690_000);
+    }
+
+    #[test]
+    fn test_check_coinbase_value() {
+        let cb = |value: i64| coinbase(&[0x01, 0x01], &[(value, &[OP_TRUE])]);
+        let spend = |value_out: i64| {
+            transaction(
+                &[([1u8; 32], 0, &[]), ([2u8; 32], 0, &[])],
+                &[(value_out, &[OP_TRUE])],
+            )
+        };
+        // fees of 1,000 at a height with 50 coins of subsidy
+        let inputs = [6_000, 4_000];
+        let check = |cb_value: i64, inputs: &[i64], height: u32| {
+            check_coinbase_value(
+                &block(&[cb(cb_value), spend(9_000)]),
+                inputs,
+                height,
+                Network::Regtest,
+            )
+        };
+        assert_eq!(check(50 * COIN + 1_000, &inputs, 1), Ok(()));
+        assert_eq!(check(50 * COIN, &inputs, 1), Ok(()));
+        assert_eq!(
+            check(50 * COIN + 1_001, &inputs, 1),
+            Err(BlockValidationError::BadCoinbaseAmount)
+        );
+        // the subsidy halves at height 150 on regtest
+        assert_eq!(
+            check(50 * COIN, &inputs, 150),
+            Err(BlockValidationError::BadCoinbaseAmount)
+        );
+        assert_eq!(check(25 * COIN + 1_000, &inputs, 150), Ok(()));
+
+        assert_eq!(
+            check(0, &[6_000], 1),
+            Err(BlockValidationError::InputValuesMismatch)
+        );
+        assert_eq!(
+            check(0, &[4_000, 4_000], 1),
+            Err(BlockValidationError::InputsBelowOutputs)
+        );
+        assert_eq!(
+            check(0, &[-1, 10_001], 1),
+            Err(BlockValidationError::InputValuesOutOfRange)
+        );
+        assert_eq!(
+            check(0, &[MAX_MONEY, 1], 1),
+            Err(BlockValidationError::InputValuesOutOfRange)
+        );
+        assert_eq!(
+            check(0, &[i64::MAX, i64::MAX], 1),
+            Err(BlockValidationError::InputValuesOutOfRange)
+        );
+
+        // each fee is in range, but not their sum
+        let two_spends = block(&[cb(0), spend(0), spend(0)]);
+        assert_eq!(
+            check_coinbase_value(
+                &two_spends,
+                &[MAX_MONEY / 2, MAX_MONEY / 2, 1, 0],
+                1,
+                Network::Regtest
+            ),
+            Err(BlockValidationError::AccumulatedFeeOutOfRange)
+        );
+        assert_eq!(
+            check_coinbase_value(
+                &two_spends,
+                &[MAX_MONEY / 2, MAX_MONEY / 2, 0, 0],
+                1,
+                Network::Regtest
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            check_coinbase_value(&block(&[spend(0)]), &[0, 0], 1, Network::Regtest),
+            Err(BlockValidationError::CoinbaseMissing)
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use core::fmt;
+use core::iter::Sum;
+use core::ops::{Add, AddAssign};
+
+use crate::header::BlockHeader;
+use crate::uint256::U256;
+
+/// Amount of work in a chain of headers, an unsigned 256-bit integer with the
+/// same wrapping arithmetic as Bitcoin Core's `arith_uint256`.
+///
+/// Displays as the 64 character hex string reported in the `chainwork` field
+/// of `getblockheader`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ChainWork(U256);
+
+impl ChainWork {
+    pub const ZERO: ChainWork = ChainWork(U256::ZERO);
+
+    /// Reads little-endian bytes, the layout of `get_block_proof` and `uint256`.
+    pub fn from_le_bytes(bytes: [u8; 32]) -> Self {
+        Self(U256::from_le_bytes(bytes))
+    }
+
+    pub fn to_le_bytes(&self) -> [u8; 32] {
+        self.0.to_le_bytes()
+    }
+
+    /// Reads big-endian bytes, the order used when displaying chainwork.
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        Self(U256::from_be_bytes(bytes))
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        self.0.to_be_bytes()
+    }
+}
+
+impl Add for ChainWork {
+    type Output = ChainWork;
+
+    fn add(self, rhs: ChainWork) -> ChainWork {
+        ChainWork(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl AddAssign for ChainWork {
+    fn add_assign(&mut self, rhs: ChainWork) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sum for ChainWork {
+    fn sum<I: Iterator<Item = ChainWork>>(iter: I) -> ChainWork {
+        iter.fold(ChainWork::ZERO, Add::add)
+    }
+}
+
+impl fmt::Display for ChainWork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+impl fmt::LowerHex for ChainWork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+/// Total work of a sequence of headers, as accumulated into `CBlockIndex::nChainWork`.
+pub fn chainwork_of(headers: &[BlockHeader]) -> ChainWork {
+    headers.iter().map(BlockHeader::block_proof).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::hex;
+
+    #[test]
+    fn test_chainwork_display() {
+        let genesis = BlockHeader::from_bytes(&hex!("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c"));
+        assert_eq!(
+            chainwork_of(&[genesis]).to_string(),
+            "0000000000000000000000000000000000000000000000000000000100010001"
+        );
+        assert_eq!(chainwork_of(&[]), ChainWork::ZERO);
+    }
+
+    #[test]
+    fn test_chainwork_arithmetic() {
+        let mut max = [0xffu8; 32];
+        let one = ChainWork::from_be_bytes({
+            let mut bytes = [0u8; 32];
+            bytes[31] = 1;
+            bytes
+        });
+
+        // carries propagate across limbs and wrap like arith_uint256
+        max[0] = 0;
+        let sum = ChainWork::from_be_bytes(max) + one;
+        let mut expected = [0u8; 32];
+        expected[0] = 1;
+        assert_eq!(sum.to_be_bytes(), expected);
+        assert_eq!(ChainWork::from_le_bytes([0xff; 32]) + one, ChainWork::ZERO);
+
+        assert!(sum > ChainWork::from_be_bytes(max));
+        assert!(one > ChainWork::ZERO);
+        assert!(ChainWork::from_be_bytes(max) < sum);
+    }
+}
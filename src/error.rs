@@ -1,13 +1,525 @@
+use crate::ffi;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+/// Errors returned by the fallible functions of this crate.
+///
+/// Header rejections are a [`HeaderRejection`], with the reject reasons Bitcoin Core reports.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BitcoinError {
-    #[error("Failed to compute block hash")]
-    BlockHashError,
+    #[error("failed to deserialize block header")]
+    HeaderDeserialize,
 
-    #[error("Failed to compute next work required")]
-    WorkRequirementError,
+    #[error("failed to deserialize block")]
+    BlockDeserialize,
 
-    #[error("Failed to deserialize block header")]
-    DeserializeError,
+    #[error(transparent)]
+    Header(#[from] HeaderRejection),
+
+    #[error("nBits of the last non-min-difficulty block required")]
+    LastNonMinDifficultyBitsRequired,
+
+    #[error(transparent)]
+    TransactionDecode(#[from] TransactionDecodeError),
+
+    #[error(transparent)]
+    ScriptVerify(#[from] ScriptVerifyError),
+
+    #[error(transparent)]
+    BlockValidation(#[from] BlockValidationError),
+}
+
+impl BitcoinError {
+    /// Maps a `ValidationError` filled in by a failed wrapper call to one of the rejection codes
+    /// of `HeaderValidationResult` in bitcoin_core_wrapper.h. Any other code, which a failure
+    /// never sets, maps to [`HeaderRejection::Unknown`] rather than panicking inside a zkVM guest.
+    pub(crate) fn from_validation_error(error: &ffi::ValidationError) -> Self {
+        match error.code {
+            ffi::HEADER_DESERIALIZE_FAILED => BitcoinError::HeaderDeserialize,
+            ffi::HEADER_LAST_BITS_REQUIRED => BitcoinError::LastNonMinDifficultyBitsRequired,
+            _ => HeaderRejection::from_validation_error(error).into(),
+        }
+    }
+}
+
+/// Header rules a header breaks, e.g. when [`crate::HeaderChainState::accept`] rejects it, with
+/// the reject reasons Bitcoin Core reports for them.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderRejection {
+    #[error("high-hash")]
+    HighHash,
+
+    #[error("prev-blk-not-found")]
+    PrevHashMismatch,
+
+    #[error("bad-diffbits: expected {expected:#010x}, got {actual:#010x}")]
+    BadDiffBits { expected: u32, actual: u32 },
+
+    #[error("time-too-old")]
+    TimeTooOld,
+
+    #[error("time-too-new")]
+    TimeTooNew,
+
+    #[error("time-timewarp-attack")]
+    TimeTimewarpAttack,
+
+    #[error("bad-version")]
+    BadVersion,
+
+    #[error("block height out of range")]
+    HeightOutOfRange,
+
+    /// A `HeaderValidationResult` this crate doesn't know, should the C++ and Rust tables drift.
+    #[error("unknown header validation result {0}")]
+    Unknown(u32),
+}
+
+impl HeaderRejection {
+    /// Maps the header rule codes of `HeaderValidationResult`, others map to `Unknown`.
+    pub(crate) fn from_validation_error(error: &ffi::ValidationError) -> Self {
+        match error.code {
+            ffi::HEADER_HIGH_HASH => HeaderRejection::HighHash,
+            ffi::HEADER_PREV_BLK_NOT_FOUND => HeaderRejection::PrevHashMismatch,
+            ffi::HEADER_BAD_DIFFBITS => HeaderRejection::BadDiffBits {
+                expected: error.expected_bits,
+                actual: error.actual_bits,
+            },
+            ffi::HEADER_TIME_TOO_OLD => HeaderRejection::TimeTooOld,
+            ffi::HEADER_TIME_TOO_NEW => HeaderRejection::TimeTooNew,
+            ffi::HEADER_TIME_TIMEWARP_ATTACK => HeaderRejection::TimeTimewarpAttack,
+            ffi::HEADER_BAD_VERSION => HeaderRejection::BadVersion,
+            ffi::HEADER_BAD_HEIGHT => HeaderRejection::HeightOutOfRange,
+            code => HeaderRejection::Unknown(code),
+        }
+    }
+}
+
+/// Reasons a transaction fails to deserialize, after the errors raised by
+/// Bitcoin Core's serialization code.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionDecodeError {
+    #[error("end of data")]
+    EndOfData,
+
+    #[error("non-canonical ReadCompactSize()")]
+    NonCanonicalCompactSize,
+
+    #[error("ReadCompactSize(): size too large")]
+    SizeTooLarge,
+
+    #[error("Superfluous witness record")]
+    SuperfluousWitnessRecord,
+
+    #[error("Unknown transaction optional data")]
+    UnknownOptionalData,
+
+    #[error("data after the end of the transaction")]
+    TrailingData,
+
+    #[error("failed to deserialize transaction")]
+    Other,
+}
+
+/// Bitcoin Core's `ScriptError` without `SCRIPT_ERR_OK`. The messages are those of `ScriptErrorString`.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptError {
+    #[error("unknown error")]
+    UnknownError,
+
+    #[error("Script evaluated without error but finished with a false/empty top stack element")]
+    EvalFalse,
+
+    #[error("OP_RETURN was encountered")]
+    OpReturn,
+
+    #[error("Script is too big")]
+    ScriptSize,
+
+    #[error("Push value size limit exceeded")]
+    PushSize,
+
+    #[error("Operation limit exceeded")]
+    OpCount,
+
+    #[error("Stack size limit exceeded")]
+    StackSize,
+
+    #[error("Signature count negative or greater than pubkey count")]
+    SigCount,
+
+    #[error("Pubkey count negative or limit exceeded")]
+    PubkeyCount,
+
+    #[error("Script failed an OP_VERIFY operation")]
+    Verify,
+
+    #[error("Script failed an OP_EQUALVERIFY operation")]
+    Equalverify,
+
+    #[error("Script failed an OP_CHECKMULTISIGVERIFY operation")]
+    Checkmultisigverify,
+
+    #[error("Script failed an OP_CHECKSIGVERIFY operation")]
+    Checksigverify,
+
+    #[error("Script failed an OP_NUMEQUALVERIFY operation")]
+    Numequalverify,
+
+    #[error("Opcode missing or not understood")]
+    BadOpcode,
+
+    #[error("Attempted to use a disabled opcode")]
+    DisabledOpcode,
+
+    #[error("Operation not valid with the current stack size")]
+    InvalidStackOperation,
+
+    #[error("Operation not valid with the current altstack size")]
+    InvalidAltstackOperation,
+
+    #[error("Invalid OP_IF construction")]
+    UnbalancedConditional,
+
+    #[error("Negative locktime")]
+    NegativeLocktime,
+
+    #[error("Locktime requirement not satisfied")]
+    UnsatisfiedLocktime,
+
+    #[error("Signature hash type missing or not understood")]
+    SigHashtype,
+
+    #[error("Non-canonical DER signature")]
+    SigDer,
+
+    #[error("Data push larger than necessary")]
+    Minimaldata,
+
+    #[error("Only push operators allowed in signatures")]
+    SigPushonly,
+
+    #[error("Non-canonical signature: S value is unnecessarily high")]
+    SigHighS,
+
+    #[error("Dummy CHECKMULTISIG argument must be zero")]
+    SigNulldummy,
+
+    #[error("Public key is neither compressed or uncompressed")]
+    Pubkeytype,
+
+    #[error("Stack size must be exactly one after execution")]
+    Cleanstack,
+
+    #[error("OP_IF/NOTIF argument must be minimal")]
+    Minimalif,
+
+    #[error("Signature must be zero for failed CHECK(MULTI)SIG operation")]
+    SigNullfail,
+
+    #[error("NOPx reserved for soft-fork upgrades")]
+    DiscourageUpgradableNops,
+
+    #[error("Witness version reserved for soft-fork upgrades")]
+    DiscourageUpgradableWitnessProgram,
+
+    #[error("Taproot version reserved for soft-fork upgrades")]
+    DiscourageUpgradableTaprootVersion,
+
+    #[error("OP_SUCCESSx reserved for soft-fork upgrades")]
+    DiscourageOpSuccess,
+
+    #[error("Public key version reserved for soft-fork upgrades")]
+    DiscourageUpgradablePubkeytype,
+
+    #[error("Witness program has incorrect length")]
+    WitnessProgramWrongLength,
+
+    #[error("Witness program was passed an empty witness")]
+    WitnessProgramWitnessEmpty,
+
+    #[error("Witness program hash mismatch")]
+    WitnessProgramMismatch,
+
+    #[error("Witness requires empty scriptSig")]
+    WitnessMalleated,
+
+    #[error("Witness requires only-redeemscript scriptSig")]
+    WitnessMalleatedP2sh,
+
+    #[error("Witness provided for non-witness script")]
+    WitnessUnexpected,
+
+    #[error("Using non-compressed keys in segwit")]
+    WitnessPubkeytype,
+
+    #[error("Invalid Schnorr signature size")]
+    SchnorrSigSize,
+
+    #[error("Invalid Schnorr signature hash type")]
+    SchnorrSigHashtype,
+
+    #[error("Invalid Schnorr signature")]
+    SchnorrSig,
+
+    #[error("Invalid Taproot control block size")]
+    TaprootWrongControlSize,
+
+    #[error("Too much signature validation relative to witness weight")]
+    TapscriptValidationWeight,
+
+    #[error("OP_CHECKMULTISIG(VERIFY) is not available in tapscript")]
+    TapscriptCheckmultisig,
+
+    #[error("OP_IF/NOTIF argument must be minimal in tapscript")]
+    TapscriptMinimalif,
+
+    #[error("Using OP_CODESEPARATOR in non-witness script")]
+    OpCodeseparator,
+
+    #[error("Signature is found in scriptCode")]
+    SigFindanddelete,
+}
+
+impl ScriptError {
+    /// Maps a `ScriptError` value from the C++ side, unknown values map to `UnknownError`.
+    pub(crate) fn from_code(code: u32) -> Self {
+        match code {
+            ffi::SCRIPT_ERR_UNKNOWN_ERROR => ScriptError::UnknownError,
+            ffi::SCRIPT_ERR_EVAL_FALSE => ScriptError::EvalFalse,
+            ffi::SCRIPT_ERR_OP_RETURN => ScriptError::OpReturn,
+            ffi::SCRIPT_ERR_SCRIPT_SIZE => ScriptError::ScriptSize,
+            ffi::SCRIPT_ERR_PUSH_SIZE => ScriptError::PushSize,
+            ffi::SCRIPT_ERR_OP_COUNT => ScriptError::OpCount,
+            ffi::SCRIPT_ERR_STACK_SIZE => ScriptError::StackSize,
+            ffi::SCRIPT_ERR_SIG_COUNT => ScriptError::SigCount,
+            ffi::SCRIPT_ERR_PUBKEY_COUNT => ScriptError::PubkeyCount,
+            ffi::SCRIPT_ERR_VERIFY => ScriptError::Verify,
+            ffi::SCRIPT_ERR_EQUALVERIFY => ScriptError::Equalverify,
+            ffi::SCRIPT_ERR_CHECKMULTISIGVERIFY => ScriptError::Checkmultisigverify,
+            ffi::SCRIPT_ERR_CHECKSIGVERIFY => ScriptError::Checksigverify,
+            ffi::SCRIPT_ERR_NUMEQUALVERIFY => ScriptError::Numequalverify,
+            ffi::SCRIPT_ERR_BAD_OPCODE => ScriptError::BadOpcode,
+            ffi::SCRIPT_ERR_DISABLED_OPCODE => ScriptError::DisabledOpcode,
+            ffi::SCRIPT_ERR_INVALID_STACK_OPERATION => ScriptError::InvalidStackOperation,
+            ffi::SCRIPT_ERR_INVALID_ALTSTACK_OPERATION => ScriptError::InvalidAltstackOperation,
+            ffi::SCRIPT_ERR_UNBALANCED_CONDITIONAL => ScriptError::UnbalancedConditional,
+            ffi::SCRIPT_ERR_NEGATIVE_LOCKTIME => ScriptError::NegativeLocktime,
+            ffi::SCRIPT_ERR_UNSATISFIED_LOCKTIME => ScriptError::UnsatisfiedLocktime,
+            ffi::SCRIPT_ERR_SIG_HASHTYPE => ScriptError::SigHashtype,
+            ffi::SCRIPT_ERR_SIG_DER => ScriptError::SigDer,
+            ffi::SCRIPT_ERR_MINIMALDATA => ScriptError::Minimaldata,
+            ffi::SCRIPT_ERR_SIG_PUSHONLY => ScriptError::SigPushonly,
+            ffi::SCRIPT_ERR_SIG_HIGH_S => ScriptError::SigHighS,
+            ffi::SCRIPT_ERR_SIG_NULLDUMMY => ScriptError::SigNulldummy,
+            ffi::SCRIPT_ERR_PUBKEYTYPE => ScriptError::Pubkeytype,
+            ffi::SCRIPT_ERR_CLEANSTACK => ScriptError::Cleanstack,
+            ffi::SCRIPT_ERR_MINIMALIF => ScriptError::Minimalif,
+            ffi::SCRIPT_ERR_SIG_NULLFAIL => ScriptError::SigNullfail,
+            ffi::SCRIPT_ERR_DISCOURAGE_UPGRADABLE_NOPS => ScriptError::DiscourageUpgradableNops,
+            ffi::SCRIPT_ERR_DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM => {
+                ScriptError::DiscourageUpgradableWitnessProgram
+            }
+            ffi::SCRIPT_ERR_DISCOURAGE_UPGRADABLE_TAPROOT_VERSION => {
+                ScriptError::DiscourageUpgradableTaprootVersion
+            }
+            ffi::SCRIPT_ERR_DISCOURAGE_OP_SUCCESS => ScriptError::DiscourageOpSuccess,
+            ffi::SCRIPT_ERR_DISCOURAGE_UPGRADABLE_PUBKEYTYPE => {
+                ScriptError::DiscourageUpgradablePubkeytype
+            }
+            ffi::SCRIPT_ERR_WITNESS_PROGRAM_WRONG_LENGTH => ScriptError::WitnessProgramWrongLength,
+            ffi::SCRIPT_ERR_WITNESS_PROGRAM_WITNESS_EMPTY => {
+                ScriptError::WitnessProgramWitnessEmpty
+            }
+            ffi::SCRIPT_ERR_WITNESS_PROGRAM_MISMATCH => ScriptError::WitnessProgramMismatch,
+            ffi::SCRIPT_ERR_WITNESS_MALLEATED => ScriptError::WitnessMalleated,
+            ffi::SCRIPT_ERR_WITNESS_MALLEATED_P2SH => ScriptError::WitnessMalleatedP2sh,
+            ffi::SCRIPT_ERR_WITNESS_UNEXPECTED => ScriptError::WitnessUnexpected,
+            ffi::SCRIPT_ERR_WITNESS_PUBKEYTYPE => ScriptError::WitnessPubkeytype,
+            ffi::SCRIPT_ERR_SCHNORR_SIG_SIZE => ScriptError::SchnorrSigSize,
+            ffi::SCRIPT_ERR_SCHNORR_SIG_HASHTYPE => ScriptError::SchnorrSigHashtype,
+            ffi::SCRIPT_ERR_SCHNORR_SIG => ScriptError::SchnorrSig,
+            ffi::SCRIPT_ERR_TAPROOT_WRONG_CONTROL_SIZE => ScriptError::TaprootWrongControlSize,
+            ffi::SCRIPT_ERR_TAPSCRIPT_VALIDATION_WEIGHT => ScriptError::TapscriptValidationWeight,
+            ffi::SCRIPT_ERR_TAPSCRIPT_CHECKMULTISIG => ScriptError::TapscriptCheckmultisig,
+            ffi::SCRIPT_ERR_TAPSCRIPT_MINIMALIF => ScriptError::TapscriptMinimalif,
+            ffi::SCRIPT_ERR_OP_CODESEPARATOR => ScriptError::OpCodeseparator,
+            ffi::SCRIPT_ERR_SIG_FINDANDDELETE => ScriptError::SigFindanddelete,
+            _ => ScriptError::UnknownError,
+        }
+    }
+}
+
+/// Flags raised by `arith_uint256::SetCompact` for nBits that don't encode a target,
+/// at least one of which is set.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("{}", match (.negative, .overflow) {
+    (true, true) => "negative compact target overflows 256 bits",
+    (true, false) => "negative compact target",
+    _ => "compact target overflows 256 bits",
+})]
+pub struct CompactError {
+    pub negative: bool,
+    pub overflow: bool,
+}
+
+/// Reasons [`crate::verify_script`] fails, after the `bitcoinconsensus_error` values
+/// of the former libbitcoinconsensus.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptVerifyError {
+    #[error("input index out of range")]
+    TxIndex,
+
+    #[error("failed to deserialize transaction")]
+    TxDeserialize,
+
+    #[error("unsupported script verification flags")]
+    InvalidFlags,
+
+    #[error("spent outputs are required to verify taproot")]
+    SpentOutputsRequired,
+
+    #[error("number of spent outputs doesn't match the number of inputs")]
+    SpentOutputsMismatch,
+
+    #[error("script verification failed: {0}")]
+    Script(ScriptError),
+
+    /// A `ScriptVerifyResult` this crate doesn't know, should the C++ and Rust tables drift.
+    #[error("unknown script verification result {0}")]
+    Unknown(u32),
+}
+
+/// Reasons the block checks reject a block. The messages are the reject reasons of
+/// Bitcoin Core's `CheckBlock`, `CheckTransaction` and `ContextualCheckBlock`.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockValidationError {
+    #[error("failed to deserialize block")]
+    Deserialize,
+
+    #[error("high-hash")]
+    HighHash,
+
+    #[error("bad-txnmrklroot")]
+    BadMerkleRoot,
+
+    #[error("bad-txns-duplicate")]
+    DuplicateTransaction,
+
+    #[error("bad-blk-length")]
+    BadLength,
+
+    #[error("bad-cb-missing")]
+    CoinbaseMissing,
+
+    #[error("bad-cb-multiple")]
+    MultipleCoinbase,
+
+    #[error("bad-txns-vin-empty")]
+    InputsEmpty,
+
+    #[error("bad-txns-vout-empty")]
+    OutputsEmpty,
+
+    #[error("bad-txns-oversize")]
+    TransactionOversize,
+
+    #[error("bad-txns-vout-negative")]
+    OutputNegative,
+
+    #[error("bad-txns-vout-toolarge")]
+    OutputTooLarge,
+
+    #[error("bad-txns-txouttotal-toolarge")]
+    OutputTotalTooLarge,
+
+    #[error("bad-txns-inputs-duplicate")]
+    DuplicateInputs,
+
+    #[error("bad-cb-length")]
+    CoinbaseLength,
+
+    #[error("bad-txns-prevout-null")]
+    PrevoutNull,
+
+    #[error("bad-blk-sigops")]
+    TooManySigops,
+
+    #[error("bad-witness-nonce-size")]
+    WitnessNonceSize,
+
+    #[error("bad-witness-merkle-match")]
+    WitnessMerkleMatch,
+
+    #[error("unexpected-witness")]
+    UnexpectedWitness,
+
+    #[error("bad-blk-weight")]
+    BadWeight,
+
+    #[error("bad-cb-height")]
+    BadCoinbaseHeight,
+
+    #[error("bad-cb-amount")]
+    BadCoinbaseAmount,
+
+    #[error("bad-txns-inputvalues-outofrange")]
+    InputValuesOutOfRange,
+
+    #[error("bad-txns-in-belowout")]
+    InputsBelowOutputs,
+
+    #[error("bad-txns-fee-outofrange")]
+    FeeOutOfRange,
+
+    #[error("bad-txns-accumulated-fee-outofrange")]
+    AccumulatedFeeOutOfRange,
+
+    #[error("number of input values doesn't match the inputs of the block")]
+    InputValuesMismatch,
+
+    #[error("block failed validation")]
+    Other,
+}
+
+impl BlockValidationError {
+    /// Maps a `BlockCheckResult` from the C++ side, `None` for `BLOCK_VALID`.
+    pub(crate) fn from_code(code: u32) -> Option<Self> {
+        let error = match code {
+            ffi::BLOCK_VALID => return None,
+            ffi::BLOCK_DESERIALIZE_FAILED => BlockValidationError::Deserialize,
+            ffi::BLOCK_HIGH_HASH => BlockValidationError::HighHash,
+            ffi::BLOCK_BAD_TXNMRKLROOT => BlockValidationError::BadMerkleRoot,
+            ffi::BLOCK_BAD_TXNS_DUPLICATE => BlockValidationError::DuplicateTransaction,
+            ffi::BLOCK_BAD_BLK_LENGTH => BlockValidationError::BadLength,
+            ffi::BLOCK_BAD_CB_MISSING => BlockValidationError::CoinbaseMissing,
+            ffi::BLOCK_BAD_CB_MULTIPLE => BlockValidationError::MultipleCoinbase,
+            ffi::BLOCK_BAD_TXNS_VIN_EMPTY => BlockValidationError::InputsEmpty,
+            ffi::BLOCK_BAD_TXNS_VOUT_EMPTY => BlockValidationError::OutputsEmpty,
+            ffi::BLOCK_BAD_TXNS_OVERSIZE => BlockValidationError::TransactionOversize,
+            ffi::BLOCK_BAD_TXNS_VOUT_NEGATIVE => BlockValidationError::OutputNegative,
+            ffi::BLOCK_BAD_TXNS_VOUT_TOOLARGE => BlockValidationError::OutputTooLarge,
+            ffi::BLOCK_BAD_TXNS_TXOUTTOTAL_TOOLARGE => BlockValidationError::OutputTotalTooLarge,
+            ffi::BLOCK_BAD_TXNS_INPUTS_DUPLICATE => BlockValidationError::DuplicateInputs,
+            ffi::BLOCK_BAD_CB_LENGTH => BlockValidationError::CoinbaseLength,
+            ffi::BLOCK_BAD_TXNS_PREVOUT_NULL => BlockValidationError::PrevoutNull,
+            ffi::BLOCK_BAD_BLK_SIGOPS => BlockValidationError::TooManySigops,
+            ffi::BLOCK_BAD_WITNESS_NONCE_SIZE => BlockValidationError::WitnessNonceSize,
+            ffi::BLOCK_BAD_WITNESS_MERKLE_MATCH => BlockValidationError::WitnessMerkleMatch,
+            ffi::BLOCK_UNEXPECTED_WITNESS => BlockValidationError::UnexpectedWitness,
+            ffi::BLOCK_BAD_BLK_WEIGHT => BlockValidationError::BadWeight,
+            ffi::BLOCK_BAD_CB_HEIGHT => BlockValidationError::BadCoinbaseHeight,
+            ffi::BLOCK_BAD_CB_AMOUNT => BlockValidationError::BadCoinbaseAmount,
+            ffi::BLOCK_BAD_TXNS_INPUTVALUES_OUTOFRANGE => {
+                BlockValidationError::InputValuesOutOfRange
+            }
+            ffi::BLOCK_BAD_TXNS_IN_BELOWOUT => BlockValidationError::InputsBelowOutputs,
+            ffi::BLOCK_BAD_TXNS_FEE_OUTOFRANGE => BlockValidationError::FeeOutOfRange,
+            ffi::BLOCK_BAD_TXNS_ACCUMULATED_FEE_OUTOFRANGE => {
+                BlockValidationError::AccumulatedFeeOutOfRange
+            }
+            ffi::BLOCK_INPUT_VALUES_MISMATCH => BlockValidationError::InputValuesMismatch,
+            _ => BlockValidationError::Other,
+        };
+        Some(error)
+    }
 }
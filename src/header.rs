@@ -0,0 +1,135 @@
+use crate::chainwork::ChainWork;
+use crate::network::Network;
+
+/// Size in bytes of a serialized block header.
+pub const HEADER_LENGTH: usize = 80;
+
+/// A Bitcoin block header, laid out like Bitcoin Core's `CBlockHeader`.
+///
+/// Hashes are kept in internal byte order, i.e. the order they appear in the
+/// serialized header, which is the reverse of how block explorers display them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockHeader {
+    pub version: i32,
+    pub prev_blockhash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    /// Parses a header from its 80-byte wire encoding (`CBlockHeader::SERIALIZE_METHODS`).
+    pub fn from_bytes(bytes: &[u8; HEADER_LENGTH]) -> Self {
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        Self {
+            version: u32_at(0) as i32,
+            prev_blockhash: bytes[4..36].try_into().unwrap(),
+            merkle_root: bytes[36..68].try_into().unwrap(),
+            time: u32_at(68),
+            bits: u32_at(72),
+            nonce: u32_at(76),
+        }
+    }
+
+    /// Serializes the header into its 80-byte wire encoding.
+    pub fn to_bytes(&self) -> [u8; HEADER_LENGTH] {
+        let mut bytes = [0u8; HEADER_LENGTH];
+        bytes[0..4].copy_from_slice(&self.version.to_le_bytes());
+        bytes[4..36].copy_from_slice(&self.prev_blockhash);
+        bytes[36..68].copy_from_slice(&self.merkle_root);
+        bytes[68..72].copy_from_slice(&self.time.to_le_bytes());
+        bytes[72..76].copy_from_slice(&self.bits.to_le_bytes());
+        bytes[76..80].copy_from_slice(&self.nonce.to_le_bytes());
+        bytes
+    }
+
+    /// Double SHA256 hash of the header, see [`crate::get_block_hash`].
+    pub fn hash(&self) -> [u8; 32] {
+        crate::get_block_hash(&self.to_bytes()).expect("80 bytes always deserialize to a header")
+    }
+
+    /// Checks the header hash against its own nBits, see [`crate::check_proof_of_work`].
+    pub fn check_proof_of_work(&self, network: Network) -> bool {
+        crate::check_proof_of_work(&self.to_bytes(), network)
+    }
+
+    /// Work represented by the header's nBits, see [`crate::get_block_proof`].
+    pub fn block_proof(&self) -> ChainWork {
+        ChainWork::from_le_bytes(
+            crate::get_block_proof(&self.to_bytes())
+                .expect("80 bytes always deserialize to a header"),
+        )
+    }
+
+    /// Returns true if this header's prev hash commits to `previous`.
+    pub fn connects_to(&self, previous: &BlockHeader) -> bool {
+        crate::check_header_connection(&self.to_bytes(), &previous.to_bytes())
+    }
+}
+
+impl From<[u8; HEADER_LENGTH]> for BlockHeader {
+    fn from(bytes: [u8; HEADER_LENGTH]) -> Self {
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl From<BlockHeader> for [u8; HEADER_LENGTH] {
+    fn from(header: BlockHeader) -> Self {
+        header.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::hex;
+
+    // block 40320
+    const HEADER_40320: [u8; 80] = hex!("010000001a231097b6ab6279c80f24674a2c8ee5b9a848e1d45715ad89b6358100000000a822bafe6ed8600e3ffce6d61d10df1927eafe9bbf677cb44c4d209f143c6ba8db8c784b5746651cce222118");
+    // block 40321
+    const HEADER_40321: [u8; 80] = hex!("0100000045720d24eae33ade0d10397a2e02989edef834701b965a9b161e864500000000993239a44a83d5c427fd3d7902789ea1a4d66a37d5848c7477a7cf47c2b071cd7690784b5746651c3af7ca03");
+
+    #[test]
+    fn test_header_fields() {
+        let header = BlockHeader::from_bytes(&HEADER_40320);
+        let mut prev_blockhash =
+            hex!("000000008135b689ad1557d4e148a8b9e58e2c4a67240fc87962abb69710231a");
+        prev_blockhash.reverse();
+
+        assert_eq!(header.version, 1);
+        assert_eq!(header.prev_blockhash, prev_blockhash);
+        assert_eq!(header.time, 1266191579);
+        assert_eq!(header.bits, 0x1c654657);
+        assert_eq!(header.nonce, 404824782);
+    }
+
+    #[test]
+    fn test_header_round_trip() {
+        let header = BlockHeader::from_bytes(&HEADER_40320);
+        assert_eq!(header.to_bytes(), HEADER_40320);
+
+        // negative versions must survive the signed/unsigned conversion
+        let mut bytes = HEADER_40321;
+        bytes[0..4].copy_from_slice(&(-2i32).to_le_bytes());
+        let header = BlockHeader::from_bytes(&bytes);
+        assert_eq!(header.version, -2);
+        assert_eq!(header.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_header_methods() {
+        let header = BlockHeader::from_bytes(&HEADER_40321);
+        let previous = BlockHeader::from_bytes(&HEADER_40320);
+
+        assert_eq!(previous.hash(), header.prev_blockhash);
+        assert!(header.check_proof_of_work(Network::Mainnet));
+        assert_eq!(
+            header.block_proof().to_le_bytes(),
+            crate::get_block_proof(&HEADER_40321).unwrap()
+        );
+        assert!(header.connects_to(&previous));
+        assert!(!previous.connects_to(&header));
+    }
+}
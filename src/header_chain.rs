@@ -0,0 +1,440 @@
+use crate::chainwork::ChainWork;
+use crate::error::{BitcoinError, HeaderRejection};
+use crate::ffi;
+use crate::header::{BlockHeader, HEADER_LENGTH};
+use crate::network::Network;
+
+/// Number of block timestamps the median time past is taken over.
+pub const MEDIAN_TIME_SPAN: usize = 11;
+
+/// Maximum number of seconds a block timestamp may be ahead of the validating node's adjusted time.
+pub const MAX_FUTURE_BLOCK_TIME: i64 = 2 * 60 * 60;
+
+/// The tip of a validated header chain, carrying everything needed to validate
+/// the next header without a block index.
+///
+/// This is a pure, in-memory equivalent of the state `AcceptBlockHeader` reads
+/// from Bitcoin Core's block index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderChainState {
+    pub network: Network,
+    pub tip: BlockHeader,
+    pub tip_height: u32,
+    /// Header at the start of the tip's difficulty adjustment period
+    pub last_retarget: BlockHeader,
+    /// Cumulative chainwork up to and including the tip
+    pub chainwork: ChainWork,
+    /// nBits of the newest block up to the tip that is at a retarget height or not at the
+    /// proof of work limit, which the testnet min-difficulty rule falls back to.
+    /// See [`get_last_non_min_difficulty_bits`](crate::get_last_non_min_difficulty_bits)
+    pub last_non_min_difficulty_bits: u32,
+    /// Timestamps of the tip and its ancestors, newest first. Only the first
+    /// `min(tip_height + 1, MEDIAN_TIME_SPAN)` entries are used.
+    pub timestamps: [u32; MEDIAN_TIME_SPAN],
+}
+
+impl HeaderChainState {
+    /// Validates `header` as the child of the tip and returns the extended chain.
+    ///
+    /// Applies the checks of `CheckBlockHeader` and `ContextualCheckBlockHeader`. `time-too-new`
+    /// compares the header against `adjusted_time`, the caller's notion of the current unix time.
+    pub fn accept(
+        &self,
+        header: &BlockHeader,
+        adjusted_time: i64,
+    ) -> Result<HeaderChainState, HeaderRejection> {
+        let state = ffi::HeaderChainState::from(self);
+        let mut next_state = state;
+        let mut error = ffi::ValidationError::default();
+        let accepted = unsafe {
+            ffi::accept_block_header(
+                &state,
+                header.to_bytes().as_ptr(),
+                adjusted_time,
+                &mut next_state,
+                &mut error,
+            )
+        };
+        if accepted {
+            Ok(HeaderChainState::from(&next_state))
+        } else {
+            Err(HeaderRejection::from_validation_error(&error))
+        }
+    }
+
+    /// Median timestamp of the tip and its ancestors, see `CBlockIndex::GetMedianTimePast`.
+    pub fn median_time_past(&self) -> i64 {
+        let count = (self.tip_height.saturating_add(1) as usize).min(MEDIAN_TIME_SPAN);
+        unsafe { ffi::get_median_time_past(self.timestamps.as_ptr(), count as u32) }
+    }
+}
+
+/// Validates `headers`, oldest first, as a chain extending `start_state` and returns the
+/// state at its end. Equivalent to calling [`HeaderChainState::accept`] on each header in
+/// turn with `adjusted_time`, but done in a single call into Bitcoin Core.
+///
+/// On failure, returns the index of the rejected header in `headers` with the reason.
+pub fn validate_header_chain(
+    start_state: &HeaderChainState,
+    headers: &[[u8; HEADER_LENGTH]],
+    adjusted_time: i64,
+) -> Result<HeaderChainState, (usize, HeaderRejection)> {
+    let state = ffi::HeaderChainState::from(start_state);
+    let mut end_state = state;
+    let mut failed_index = 0u32;
+    let mut error = ffi::ValidationError::default();
+    let valid = unsafe {
+        ffi::validate_header_chain(
+            &state,
+            headers.as_ptr().cast(),
+            headers.len() as u32,
+            adjusted_time,
+            &mut end_state,
+            &mut failed_index,
+            &mut error,
+        )
+    };
+    if valid {
+        Ok(HeaderChainState::from(&end_state))
+    } else {
+        Err((
+            failed_index as usize,
+            HeaderRejection::from_validation_error(&error),
+        ))
+    }
+}
+
+/// Median timestamp of eleven consecutive headers, see `CBlockIndex::GetMedianTimePast`.
+pub fn get_median_time_past(headers: &[BlockHeader; MEDIAN_TIME_SPAN]) -> i64 {
+    let timestamps = headers.map(|header| header.time);
+    unsafe { ffi::get_median_time_past(timestamps.as_ptr(), MEDIAN_TIME_SPAN as u32) }
+}
+
+/// Checks `header`'s timestamp like `ContextualCheckBlockHeader`: it must be later than the
+/// median time past of the eleven `previous_headers` and at most [`MAX_FUTURE_BLOCK_TIME`]
+/// seconds ahead of `adjusted_time`, the caller's notion of the current unix time.
+pub fn check_header_timestamp(
+    header: &BlockHeader,
+    previous_headers: &[BlockHeader; MEDIAN_TIME_SPAN],
+    adjusted_time: i64,
+) -> Result<(), BitcoinError> {
+    let timestamps = previous_headers.map(|header| header.time);
+    let mut error = ffi::ValidationError::default();
+    let valid = unsafe {
+        ffi::check_header_time(
+            header.to_bytes().as_ptr(),
+            timestamps.as_ptr(),
+            MEDIAN_TIME_SPAN as u32,
+            adjusted_time,
+            &mut error,
+        )
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(BitcoinError::from_validation_error(&error))
+    }
+}
+
+impl From<&HeaderChainState> for ffi::HeaderChainState {
+    fn from(state: &HeaderChainState) -> Self {
+        Self {
+            tip_header: state.tip.to_bytes(),
+            tip_height: state.tip_height,
+            last_retarget_header: state.last_retarget.to_bytes(),
+            chainwork: state.chainwork.to_le_bytes(),
+            last_non_min_difficulty_bits: state.last_non_min_difficulty_bits,
+            timestamps: state.timestamps,
+            network: state.network,
+        }
+    }
+}
+
+impl From<&ffi::HeaderChainState> for HeaderChainState {
+    fn from(state: &ffi::HeaderChainState) -> Self {
+        Self {
+            network: state.network,
+            tip: BlockHeader::from_bytes(&state.tip_header),
+            tip_height: state.tip_height,
+            last_retarget: BlockHeader::from_bytes(&state.last_retarget_header),
+            chainwork: ChainWork::from_le_bytes(state.chainwork),
+            last_non_min_difficulty_bits: state.last_non_min_difficulty_bits,
+            timestamps: state.timestamps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mine;
+    use alloy_primitives::hex;
+
+    const HEADER_38304: [u8; 80] = hex!("01000000858a5c6d458833aa83f7b7e56d71c604cb71165ebb8104b82f64de8d00000000e408c11029b5fdbb92ea0eeb8dfa138ffa3acce0f69d7deebeb1400c85042e01723f6b4bc38c001d09bd8bd5");
+    const HEADER_40319: [u8; 80] = hex!("01000000a4eaafee7ae520b206e56ae73af34cdeedf022fd000081ef74a4830600000000396f6849a2da8ddd7c3452647f30c2fe9feb3e5c089bd1eb36808374ca36d7b4f986784bc38c001d6047eb01");
+    const HEADER_40320: [u8; 80] = hex!("010000001a231097b6ab6279c80f24674a2c8ee5b9a848e1d45715ad89b6358100000000a822bafe6ed8600e3ffce6d61d10df1927eafe9bbf677cb44c4d209f143c6ba8db8c784b5746651cce222118");
+    const HEADER_40321: [u8; 80] = hex!("0100000045720d24eae33ade0d10397a2e02989edef834701b965a9b161e864500000000993239a44a83d5c427fd3d7902789ea1a4d66a37d5848c7477a7cf47c2b071cd7690784b5746651c3af7ca03");
+    // Later than every test header, so time-too-new only rejects where a test asks for it
+    const NOW: i64 = 1_800_000_000;
+    const REGTEST_GENESIS: [u8; 80] = hex!("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff7f2002000000");
+
+    // State at block 40319. The real ancestor timestamps aren't needed, only
+    // a median time past below the timestamps of the following blocks
+    fn state_at_40319() -> HeaderChainState {
+        let tip = BlockHeader::from_bytes(&HEADER_40319);
+        HeaderChainState {
+            network: Network::Mainnet,
+            tip,
+            tip_height: 40319,
+            last_retarget: BlockHeader::from_bytes(&HEADER_38304),
+            chainwork: ChainWork::from_be_bytes(hex!(
+                "0000000000000000000000000000000000000000000000000000aa80bfeea100"
+            )),
+            last_non_min_difficulty_bits: tip.bits,
+            timestamps: [tip.time; MEDIAN_TIME_SPAN],
+        }
+    }
+
+    #[test]
+    fn test_accept_across_retarget() {
+        let header_40320 = BlockHeader::from_bytes(&HEADER_40320);
+        let state = state_at_40319().accept(&header_40320, NOW).unwrap();
+        assert_eq!(state.tip, header_40320);
+        assert_eq!(state.tip_height, 40320);
+        assert_eq!(state.last_retarget, header_40320);
+        assert_eq!(
+            state.chainwork,
+            ChainWork::from_be_bytes(hex!(
+                "0000000000000000000000000000000000000000000000000000aa83470b0222"
+            ))
+        );
+        assert_eq!(state.timestamps[0], header_40320.time);
+        assert_eq!(state.timestamps[1], state_at_40319().tip.time);
+
+        let header_40321 = BlockHeader::from_bytes(&HEADER_40321);
+        let state = state.accept(&header_40321, NOW).unwrap();
+        assert_eq!(state.tip_height, 40321);
+        assert_eq!(state.last_retarget, header_40320);
+    }
+
+    #[test]
+    fn test_validate_header_chain() {
+        let headers = [HEADER_40320, HEADER_40321];
+        let state = validate_header_chain(&state_at_40319(), &headers, NOW).unwrap();
+        let expected = state_at_40319()
+            .accept(&BlockHeader::from_bytes(&HEADER_40320), NOW)
+            .and_then(|state| state.accept(&BlockHeader::from_bytes(&HEADER_40321), NOW))
+            .unwrap();
+        assert_eq!(state, expected);
+
+        assert_eq!(
+            validate_header_chain(&state_at_40319(), &[], NOW),
+            Ok(state_at_40319())
+        );
+        assert_eq!(
+            validate_header_chain(&state_at_40319(), &[HEADER_40320, HEADER_40320], NOW),
+            Err((1, HeaderRejection::PrevHashMismatch))
+        );
+        assert_eq!(
+            validate_header_chain(&state_at_40319(), &[HEADER_40321], NOW),
+            Err((0, HeaderRejection::PrevHashMismatch))
+        );
+
+        // time-too-new applies to every header, like accept
+        let last_time = BlockHeader::from_bytes(&HEADER_40321).time as i64;
+        assert_eq!(
+            validate_header_chain(
+                &state_at_40319(),
+                &headers,
+                last_time - MAX_FUTURE_BLOCK_TIME
+            ),
+            Ok(expected)
+        );
+        assert_eq!(
+            validate_header_chain(
+                &state_at_40319(),
+                &headers,
+                last_time - MAX_FUTURE_BLOCK_TIME - 1
+            ),
+            Err((1, HeaderRejection::TimeTooNew))
+        );
+    }
+
+    #[test]
+    fn test_validate_header_chain_regtest() {
+        // A long regtest chain also exercises the timestamp ring and the median time past
+        let genesis = BlockHeader::from_bytes(&REGTEST_GENESIS);
+        let start = HeaderChainState {
+            network: Network::Regtest,
+            tip: genesis,
+            tip_height: 0,
+            last_retarget: genesis,
+            chainwork: genesis.block_proof(),
+            last_non_min_difficulty_bits: genesis.bits,
+            timestamps: [genesis.time; MEDIAN_TIME_SPAN],
+        };
+        let mut headers = Vec::new();
+        let mut state = start;
+        for _ in 0..30 {
+            let header = mine(BlockHeader {
+                version: 4,
+                prev_blockhash: state.tip.hash(),
+                merkle_root: [0; 32],
+                time: state.tip.time + 1,
+                bits: genesis.bits,
+                nonce: 0,
+            });
+            state = state.accept(&header, NOW).unwrap();
+            headers.push(header.to_bytes());
+        }
+        assert_eq!(validate_header_chain(&start, &headers, NOW), Ok(state));
+        // every regtest block is at the proof of work limit
+        assert_eq!(state.last_non_min_difficulty_bits, genesis.bits);
+
+        // Back to the median time past of the previous eleven blocks
+        headers[20] = mine(BlockHeader {
+            time: BlockHeader::from_bytes(&headers[14]).time,
+            ..BlockHeader::from_bytes(&headers[20])
+        })
+        .to_bytes();
+        assert_eq!(
+            validate_header_chain(&start, &headers, NOW),
+            Err((20, HeaderRejection::TimeTooOld))
+        );
+    }
+
+    #[test]
+    fn test_accept_rejections() {
+        let state = state_at_40319();
+
+        let mut high_hash = BlockHeader::from_bytes(&HEADER_40320);
+        high_hash.nonce += 1;
+        assert_eq!(
+            state.accept(&high_hash, NOW),
+            Err(HeaderRejection::HighHash)
+        );
+
+        let orphan = BlockHeader::from_bytes(&HEADER_40321);
+        assert_eq!(
+            state.accept(&orphan, NOW),
+            Err(HeaderRejection::PrevHashMismatch)
+        );
+
+        let mut wrong_retarget = state;
+        wrong_retarget.last_retarget.time += 24 * 60 * 60;
+        assert_eq!(
+            wrong_retarget.accept(&BlockHeader::from_bytes(&HEADER_40320), NOW),
+            Err(HeaderRejection::BadDiffBits {
+                expected: 0x1c5b3869,
+                actual: 0x1c654657
+            })
+        );
+
+        let mut future_ancestors = state;
+        future_ancestors.timestamps = [u32::MAX; MEDIAN_TIME_SPAN];
+        assert_eq!(
+            future_ancestors.accept(&BlockHeader::from_bytes(&HEADER_40320), NOW),
+            Err(HeaderRejection::TimeTooOld)
+        );
+
+        let mut overflowing_height = state;
+        overflowing_height.tip_height = u32::MAX;
+        assert_eq!(
+            overflowing_height.median_time_past(),
+            state.median_time_past()
+        );
+        assert_eq!(
+            overflowing_height.accept(&BlockHeader::from_bytes(&HEADER_40320), NOW),
+            Err(HeaderRejection::HeightOutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_accept_bad_version() {
+        let genesis = BlockHeader::from_bytes(&REGTEST_GENESIS);
+        let state = HeaderChainState {
+            network: Network::Regtest,
+            tip: genesis,
+            tip_height: 0,
+            last_retarget: genesis,
+            chainwork: genesis.block_proof(),
+            last_non_min_difficulty_bits: genesis.bits,
+            timestamps: [genesis.time; MEDIAN_TIME_SPAN],
+        };
+        let child = BlockHeader {
+            version: 1,
+            prev_blockhash: genesis.hash(),
+            merkle_root: [0u8; 32],
+            time: genesis.time + 600,
+            bits: genesis.bits,
+            nonce: 0,
+        };
+
+        // BIP34, BIP65 and BIP66 are active from height 1 on regtest
+        assert_eq!(
+            state.accept(&mine(child), NOW),
+            Err(HeaderRejection::BadVersion)
+        );
+        let child = mine(BlockHeader {
+            version: 4,
+            ..child
+        });
+        let state = state.accept(&child, NOW).unwrap();
+        assert_eq!(state.tip_height, 1);
+        assert_eq!(state.timestamps[..2], [child.time, genesis.time]);
+    }
+
+    #[test]
+    fn test_median_time_past() {
+        let header = BlockHeader::from_bytes(&HEADER_40320);
+        let times = [9, 3, 7, 1, 11, 5, 2, 10, 4, 8, 6];
+        let headers = times.map(|time| BlockHeader { time, ..header });
+        assert_eq!(get_median_time_past(&headers), 6);
+
+        // near genesis only the available ancestors are used
+        let mut state = state_at_40319();
+        state.tip_height = 1;
+        state.timestamps = [100, 200, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(state.median_time_past(), 200);
+    }
+
+    #[test]
+    fn test_check_header_timestamp() {
+        let previous = [BlockHeader::from_bytes(&HEADER_40319); MEDIAN_TIME_SPAN];
+        let mtp = get_median_time_past(&previous);
+        let header = BlockHeader::from_bytes(&HEADER_40320);
+        let at = |time| BlockHeader { time, ..header };
+        let now = header.time as i64;
+
+        assert_eq!(check_header_timestamp(&header, &previous, now), Ok(()));
+        assert_eq!(
+            check_header_timestamp(&at(mtp as u32), &previous, now),
+            Err(BitcoinError::Header(HeaderRejection::TimeTooOld))
+        );
+        assert_eq!(
+            check_header_timestamp(&at(mtp as u32 + 1), &previous, now),
+            Ok(())
+        );
+
+        let max_time = (now + MAX_FUTURE_BLOCK_TIME) as u32;
+        assert_eq!(
+            check_header_timestamp(&at(max_time), &previous, now),
+            Ok(())
+        );
+        assert_eq!(
+            check_header_timestamp(&at(max_time + 1), &previous, now),
+            Err(BitcoinError::Header(HeaderRejection::TimeTooNew))
+        );
+    }
+
+    #[test]
+    fn test_accept_adjusted_time() {
+        let header = BlockHeader::from_bytes(&HEADER_40320);
+        let now = header.time as i64 - MAX_FUTURE_BLOCK_TIME;
+        assert!(state_at_40319().accept(&header, now).is_ok());
+        assert_eq!(
+            state_at_40319().accept(&header, now - 1),
+            Err(HeaderRejection::TimeTooNew)
+        );
+    }
+}
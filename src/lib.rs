@@ -1,27 +1,427 @@
-pub mod error;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
 
-use crate::error::BitcoinError;
+pub mod block;
+pub mod chainwork;
+pub mod error;
+pub mod header;
+pub mod header_chain;
+pub mod merkle;
+pub mod network;
+pub mod script;
+pub mod signature;
+pub mod target;
+#[cfg(test)]
+mod test_utils;
+pub mod transaction;
+mod uint256;
 
-use eyre::Result;
+pub use crate::block::{
+    check_block, check_coinbase_height, check_coinbase_value, check_witness_commitment,
+    coinbase_height, compute_witness_commitment, get_block_subsidy, get_witness_commitment_index,
+};
+pub use crate::chainwork::{chainwork_of, ChainWork};
+pub use crate::error::{
+    BitcoinError, BlockValidationError, CompactError, HeaderRejection, ScriptError,
+    ScriptVerifyError, TransactionDecodeError,
+};
+pub use crate::header::BlockHeader;
+pub use crate::header_chain::{
+    check_header_timestamp, get_median_time_past, validate_header_chain, HeaderChainState,
+};
+pub use crate::merkle::{
+    block_merkle_root, block_witness_merkle_root, compute_merkle_root, merkle_branch,
+    verify_merkle_branch, verify_merkle_branch_with_coinbase,
+};
+pub use crate::network::Network;
+pub use crate::script::verify_script;
+pub use crate::signature::{verify_ecdsa, verify_schnorr};
+pub use crate::target::{
+    check_hash_against_target, check_proof_of_work_with_limit, difficulty_from_compact,
+    target_from_compact, target_to_work, ShareCheck, Target,
+};
+pub use crate::transaction::{OutPoint, Transaction, TxIn, TxOut};
 
 mod ffi {
+    use crate::header_chain::MEDIAN_TIME_SPAN;
+    use crate::network::Network;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub(crate) struct HeaderChainState {
+        pub(crate) tip_header: [u8; 80],
+        pub(crate) tip_height: u32,
+        pub(crate) last_retarget_header: [u8; 80],
+        pub(crate) chainwork: [u8; 32],
+        pub(crate) last_non_min_difficulty_bits: u32,
+        pub(crate) timestamps: [u32; MEDIAN_TIME_SPAN],
+        pub(crate) network: Network,
+    }
+
+    pub(crate) const HEADER_HIGH_HASH: u32 = 1;
+    pub(crate) const HEADER_PREV_BLK_NOT_FOUND: u32 = 2;
+    pub(crate) const HEADER_BAD_DIFFBITS: u32 = 3;
+    pub(crate) const HEADER_TIME_TOO_OLD: u32 = 4;
+    pub(crate) const HEADER_TIME_TIMEWARP_ATTACK: u32 = 5;
+    pub(crate) const HEADER_BAD_VERSION: u32 = 6;
+    pub(crate) const HEADER_TIME_TOO_NEW: u32 = 8;
+    pub(crate) const HEADER_DESERIALIZE_FAILED: u32 = 9;
+    pub(crate) const HEADER_BAD_HEIGHT: u32 = 10;
+    pub(crate) const HEADER_LAST_BITS_REQUIRED: u32 = 11;
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub(crate) struct ValidationError {
+        pub(crate) code: u32,
+        pub(crate) expected_bits: u32,
+        pub(crate) actual_bits: u32,
+    }
+
+    pub(crate) const TX_OK: u32 = 0;
+    pub(crate) const TX_END_OF_DATA: u32 = 1;
+    pub(crate) const TX_NON_CANONICAL_COMPACT_SIZE: u32 = 2;
+    pub(crate) const TX_SIZE_TOO_LARGE: u32 = 3;
+    pub(crate) const TX_SUPERFLUOUS_WITNESS_RECORD: u32 = 4;
+    pub(crate) const TX_UNKNOWN_OPTIONAL_DATA: u32 = 5;
+    pub(crate) const TX_TRAILING_DATA: u32 = 6;
+
+    pub(crate) const BLOCK_VALID: u32 = 0;
+    pub(crate) const BLOCK_DESERIALIZE_FAILED: u32 = 1;
+    pub(crate) const BLOCK_HIGH_HASH: u32 = 2;
+    pub(crate) const BLOCK_BAD_TXNMRKLROOT: u32 = 3;
+    pub(crate) const BLOCK_BAD_TXNS_DUPLICATE: u32 = 4;
+    pub(crate) const BLOCK_BAD_BLK_LENGTH: u32 = 5;
+    pub(crate) const BLOCK_BAD_CB_MISSING: u32 = 6;
+    pub(crate) const BLOCK_BAD_CB_MULTIPLE: u32 = 7;
+    pub(crate) const BLOCK_BAD_TXNS_VIN_EMPTY: u32 = 8;
+    pub(crate) const BLOCK_BAD_TXNS_VOUT_EMPTY: u32 = 9;
+    pub(crate) const BLOCK_BAD_TXNS_OVERSIZE: u32 = 10;
+    pub(crate) const BLOCK_BAD_TXNS_VOUT_NEGATIVE: u32 = 11;
+    pub(crate) const BLOCK_BAD_TXNS_VOUT_TOOLARGE: u32 = 12;
+    pub(crate) const BLOCK_BAD_TXNS_TXOUTTOTAL_TOOLARGE: u32 = 13;
+    pub(crate) const BLOCK_BAD_TXNS_INPUTS_DUPLICATE: u32 = 14;
+    pub(crate) const BLOCK_BAD_CB_LENGTH: u32 = 15;
+    pub(crate) const BLOCK_BAD_TXNS_PREVOUT_NULL: u32 = 16;
+    pub(crate) const BLOCK_BAD_BLK_SIGOPS: u32 = 17;
+    pub(crate) const BLOCK_BAD_WITNESS_NONCE_SIZE: u32 = 19;
+    pub(crate) const BLOCK_BAD_WITNESS_MERKLE_MATCH: u32 = 20;
+    pub(crate) const BLOCK_UNEXPECTED_WITNESS: u32 = 21;
+    pub(crate) const BLOCK_BAD_BLK_WEIGHT: u32 = 22;
+    pub(crate) const BLOCK_BAD_CB_HEIGHT: u32 = 23;
+    pub(crate) const BLOCK_BAD_CB_AMOUNT: u32 = 24;
+    pub(crate) const BLOCK_BAD_TXNS_INPUTVALUES_OUTOFRANGE: u32 = 25;
+    pub(crate) const BLOCK_BAD_TXNS_IN_BELOWOUT: u32 = 26;
+    pub(crate) const BLOCK_BAD_TXNS_FEE_OUTOFRANGE: u32 = 27;
+    pub(crate) const BLOCK_BAD_TXNS_ACCUMULATED_FEE_OUTOFRANGE: u32 = 28;
+    pub(crate) const BLOCK_INPUT_VALUES_MISMATCH: u32 = 29;
+
+    pub(crate) const VERIFY_OK: u32 = 0;
+    pub(crate) const VERIFY_ERR_TX_INDEX: u32 = 1;
+    pub(crate) const VERIFY_ERR_TX_DESERIALIZE: u32 = 2;
+    pub(crate) const VERIFY_ERR_INVALID_FLAGS: u32 = 3;
+    pub(crate) const VERIFY_ERR_SPENT_OUTPUTS_REQUIRED: u32 = 4;
+    pub(crate) const VERIFY_ERR_SPENT_OUTPUTS_MISMATCH: u32 = 5;
+    pub(crate) const VERIFY_ERR_SCRIPT: u32 = 6;
+
+    // ScriptError_t in the vendored script/script_error.h, without SCRIPT_ERR_OK. Must be kept
+    // in sync, bitcoin_core_wrapper.cpp asserts the last value
+    pub(crate) const SCRIPT_ERR_UNKNOWN_ERROR: u32 = 1;
+    pub(crate) const SCRIPT_ERR_EVAL_FALSE: u32 = 2;
+    pub(crate) const SCRIPT_ERR_OP_RETURN: u32 = 3;
+    pub(crate) const SCRIPT_ERR_SCRIPT_SIZE: u32 = 4;
+    pub(crate) const SCRIPT_ERR_PUSH_SIZE: u32 = 5;
+    pub(crate) const SCRIPT_ERR_OP_COUNT: u32 = 6;
+    pub(crate) const SCRIPT_ERR_STACK_SIZE: u32 = 7;
+    pub(crate) const SCRIPT_ERR_SIG_COUNT: u32 = 8;
+    pub(crate) const SCRIPT_ERR_PUBKEY_COUNT: u32 = 9;
+    pub(crate) const SCRIPT_ERR_VERIFY: u32 = 10;
+    pub(crate) const SCRIPT_ERR_EQUALVERIFY: u32 = 11;
+    pub(crate) const SCRIPT_ERR_CHECKMULTISIGVERIFY: u32 = 12;
+    pub(crate) const SCRIPT_ERR_CHECKSIGVERIFY: u32 = 13;
+    pub(crate) const SCRIPT_ERR_NUMEQUALVERIFY: u32 = 14;
+    pub(crate) const SCRIPT_ERR_BAD_OPCODE: u32 = 15;
+    pub(crate) const SCRIPT_ERR_DISABLED_OPCODE: u32 = 16;
+    pub(crate) const SCRIPT_ERR_INVALID_STACK_OPERATION: u32 = 17;
+    pub(crate) const SCRIPT_ERR_INVALID_ALTSTACK_OPERATION: u32 = 18;
+    pub(crate) const SCRIPT_ERR_UNBALANCED_CONDITIONAL: u32 = 19;
+    pub(crate) const SCRIPT_ERR_NEGATIVE_LOCKTIME: u32 = 20;
+    pub(crate) const SCRIPT_ERR_UNSATISFIED_LOCKTIME: u32 = 21;
+    pub(crate) const SCRIPT_ERR_SIG_HASHTYPE: u32 = 22;
+    pub(crate) const SCRIPT_ERR_SIG_DER: u32 = 23;
+    pub(crate) const SCRIPT_ERR_MINIMALDATA: u32 = 24;
+    pub(crate) const SCRIPT_ERR_SIG_PUSHONLY: u32 = 25;
+    pub(crate) const SCRIPT_ERR_SIG_HIGH_S: u32 = 26;
+    pub(crate) const SCRIPT_ERR_SIG_NULLDUMMY: u32 = 27;
+    pub(crate) const SCRIPT_ERR_PUBKEYTYPE: u32 = 28;
+    pub(crate) const SCRIPT_ERR_CLEANSTACK: u32 = 29;
+    pub(crate) const SCRIPT_ERR_MINIMALIF: u32 = 30;
+    pub(crate) const SCRIPT_ERR_SIG_NULLFAIL: u32 = 31;
+    pub(crate) const SCRIPT_ERR_DISCOURAGE_UPGRADABLE_NOPS: u32 = 32;
+    pub(crate) const SCRIPT_ERR_DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM: u32 = 33;
+    pub(crate) const SCRIPT_ERR_DISCOURAGE_UPGRADABLE_TAPROOT_VERSION: u32 = 34;
+    pub(crate) const SCRIPT_ERR_DISCOURAGE_OP_SUCCESS: u32 = 35;
+    pub(crate) const SCRIPT_ERR_DISCOURAGE_UPGRADABLE_PUBKEYTYPE: u32 = 36;
+    pub(crate) const SCRIPT_ERR_WITNESS_PROGRAM_WRONG_LENGTH: u32 = 37;
+    pub(crate) const SCRIPT_ERR_WITNESS_PROGRAM_WITNESS_EMPTY: u32 = 38;
+    pub(crate) const SCRIPT_ERR_WITNESS_PROGRAM_MISMATCH: u32 = 39;
+    pub(crate) const SCRIPT_ERR_WITNESS_MALLEATED: u32 = 40;
+    pub(crate) const SCRIPT_ERR_WITNESS_MALLEATED_P2SH: u32 = 41;
+    pub(crate) const SCRIPT_ERR_WITNESS_UNEXPECTED: u32 = 42;
+    pub(crate) const SCRIPT_ERR_WITNESS_PUBKEYTYPE: u32 = 43;
+    pub(crate) const SCRIPT_ERR_SCHNORR_SIG_SIZE: u32 = 44;
+    pub(crate) const SCRIPT_ERR_SCHNORR_SIG_HASHTYPE: u32 = 45;
+    pub(crate) const SCRIPT_ERR_SCHNORR_SIG: u32 = 46;
+    pub(crate) const SCRIPT_ERR_TAPROOT_WRONG_CONTROL_SIZE: u32 = 47;
+    pub(crate) const SCRIPT_ERR_TAPSCRIPT_VALIDATION_WEIGHT: u32 = 48;
+    pub(crate) const SCRIPT_ERR_TAPSCRIPT_CHECKMULTISIG: u32 = 49;
+    pub(crate) const SCRIPT_ERR_TAPSCRIPT_MINIMALIF: u32 = 50;
+    pub(crate) const SCRIPT_ERR_OP_CODESEPARATOR: u32 = 51;
+    pub(crate) const SCRIPT_ERR_SIG_FINDANDDELETE: u32 = 52;
+
+    #[repr(C)]
+    pub(crate) struct TransactionHandle {
+        _private: [u8; 0],
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub(crate) struct TransactionInfo {
+        pub(crate) version: u32,
+        pub(crate) lock_time: u32,
+        pub(crate) input_count: u32,
+        pub(crate) output_count: u32,
+        pub(crate) txid: [u8; 32],
+        pub(crate) wtxid: [u8; 32],
+        pub(crate) weight: u32,
+    }
+
+    #[repr(C)]
+    pub(crate) struct TransactionInputInfo {
+        pub(crate) prevout_hash: [u8; 32],
+        pub(crate) prevout_n: u32,
+        pub(crate) sequence: u32,
+        pub(crate) script_sig: *const u8,
+        pub(crate) script_sig_len: u32,
+        pub(crate) witness_len: u32,
+    }
+
+    impl Default for TransactionInputInfo {
+        fn default() -> Self {
+            Self {
+                prevout_hash: [0u8; 32],
+                prevout_n: 0,
+                sequence: 0,
+                script_sig: core::ptr::null(),
+                script_sig_len: 0,
+                witness_len: 0,
+            }
+        }
+    }
+
+    #[repr(C)]
+    pub(crate) struct TransactionOutputInfo {
+        pub(crate) value: i64,
+        pub(crate) script_pubkey: *const u8,
+        pub(crate) script_pubkey_len: u32,
+    }
+
+    impl Default for TransactionOutputInfo {
+        fn default() -> Self {
+            Self {
+                value: 0,
+                script_pubkey: core::ptr::null(),
+                script_pubkey_len: 0,
+            }
+        }
+    }
+
     extern "C" {
         pub(crate) fn sha256_hash(input: *const u8, input_len: u32, output: *mut u8);
+        pub(crate) fn sha256d_64(input: *const u8, blocks: u32, output: *mut u8);
         pub(crate) fn get_header_hash(header_bytes: *const u8, block_hash: *mut u8) -> bool;
-        pub(crate) fn check_proof_of_work(header_bytes: *const u8) -> bool;
-        pub(crate) fn get_retarget_height(height: u32) -> u32;
+        pub(crate) fn check_proof_of_work(header_bytes: *const u8, network: Network) -> bool;
+        pub(crate) fn check_proof_of_work_with_limit(
+            header_bytes: *const u8,
+            pow_limit: *const u8,
+        ) -> bool;
+        pub(crate) fn check_hash_against_target(
+            header_bytes: *const u8,
+            target: *const u8,
+            network: Network,
+            meets_network_target: *mut bool,
+        ) -> bool;
+        pub(crate) fn get_retarget_height(height: u32, network: Network) -> u32;
+        pub(crate) fn permitted_difficulty_transition(
+            height: u32,
+            old_nbits: u32,
+            new_nbits: u32,
+            network: Network,
+        ) -> bool;
         pub(crate) fn validate_next_work_required(
             last_retarget_header_bytes: *const u8,
             previous_height: u32,
             previous_header_bytes: *const u8,
             header_bytes: *const u8,
+            last_non_min_difficulty_bits: *const u32,
+            network: Network,
             next_retarget_header_bytes: *mut u8,
+            error: *mut ValidationError,
         ) -> bool;
+        pub(crate) fn get_last_non_min_difficulty_bits(
+            last_non_min_difficulty_bits: u32,
+            height: u32,
+            header_bits: u32,
+            network: Network,
+        ) -> u32;
         pub(crate) fn get_block_proof(header_bytes: *const u8, proof: *mut u8) -> bool;
+        pub(crate) fn target_from_compact(
+            nbits: u32,
+            target: *mut u8,
+            negative: *mut bool,
+            overflow: *mut bool,
+        );
+        pub(crate) fn target_to_compact(target: *const u8) -> u32;
+        pub(crate) fn difficulty_from_compact(nbits: u32) -> f64;
+        pub(crate) fn target_to_work(target: *const u8, work: *mut u8);
         pub(crate) fn check_header_connection(
             header_bytes: *const u8,
             previous_header_bytes: *const u8,
         ) -> bool;
+        pub(crate) fn accept_block_header(
+            state: *const HeaderChainState,
+            header_bytes: *const u8,
+            adjusted_time: i64,
+            next_state: *mut HeaderChainState,
+            error: *mut ValidationError,
+        ) -> bool;
+        pub(crate) fn validate_header_chain(
+            state: *const HeaderChainState,
+            headers: *const u8,
+            count: u32,
+            adjusted_time: i64,
+            end_state: *mut HeaderChainState,
+            failed_index: *mut u32,
+            error: *mut ValidationError,
+        ) -> bool;
+        pub(crate) fn compute_merkle_root(
+            hashes: *const u8,
+            count: u32,
+            root: *mut u8,
+            mutated: *mut bool,
+        );
+        pub(crate) fn block_merkle_root(
+            block_bytes: *const u8,
+            block_len: u32,
+            root: *mut u8,
+            mutated: *mut bool,
+        ) -> bool;
+        pub(crate) fn block_witness_merkle_root(
+            block_bytes: *const u8,
+            block_len: u32,
+            root: *mut u8,
+            mutated: *mut bool,
+        ) -> bool;
+        pub(crate) fn compute_merkle_branch(
+            hashes: *const u8,
+            count: u32,
+            position: u32,
+            branch: *mut u8,
+            branch_len: *mut u32,
+        ) -> bool;
+        pub(crate) fn verify_merkle_branch(
+            leaf: *const u8,
+            branch: *const u8,
+            branch_len: u32,
+            position: u32,
+            root: *const u8,
+        ) -> bool;
+        pub(crate) fn transaction_deserialize(
+            tx_bytes: *const u8,
+            tx_len: u32,
+            handle: *mut *mut TransactionHandle,
+        ) -> u32;
+        pub(crate) fn transaction_free(handle: *mut TransactionHandle);
+        pub(crate) fn transaction_info(
+            handle: *const TransactionHandle,
+            info: *mut TransactionInfo,
+        );
+        pub(crate) fn transaction_input(
+            handle: *const TransactionHandle,
+            index: u32,
+            input: *mut TransactionInputInfo,
+        );
+        pub(crate) fn transaction_input_witness(
+            handle: *const TransactionHandle,
+            index: u32,
+            item: u32,
+            item_len: *mut u32,
+        ) -> *const u8;
+        pub(crate) fn transaction_output(
+            handle: *const TransactionHandle,
+            index: u32,
+            output: *mut TransactionOutputInfo,
+        );
+        pub(crate) fn verify_script(
+            script_sig: *const u8,
+            script_sig_len: u32,
+            script_pubkey: *const u8,
+            script_pubkey_len: u32,
+            witness_items: *const *const u8,
+            witness_item_lens: *const u32,
+            witness_len: u32,
+            flags: u32,
+            tx_bytes: *const u8,
+            tx_len: u32,
+            input_index: u32,
+            amount: i64,
+            spent_outputs: *const TransactionOutputInfo,
+            spent_outputs_len: u32,
+            script_error: *mut u32,
+        ) -> u32;
+        pub(crate) fn verify_ecdsa(
+            pubkey: *const u8,
+            pubkey_len: u32,
+            sig: *const u8,
+            sig_len: u32,
+            msg_hash: *const u8,
+        ) -> bool;
+        pub(crate) fn verify_schnorr(pubkey: *const u8, sig: *const u8, msg: *const u8) -> bool;
+        pub(crate) fn check_block(block_bytes: *const u8, block_len: u32, network: Network) -> u32;
+        pub(crate) fn get_witness_commitment_index(tx_bytes: *const u8, tx_len: u32) -> i32;
+        pub(crate) fn compute_witness_commitment(
+            block_bytes: *const u8,
+            block_len: u32,
+            commitment: *mut u8,
+        ) -> u32;
+        pub(crate) fn check_witness_commitment(block_bytes: *const u8, block_len: u32) -> u32;
+        pub(crate) fn coinbase_height(tx_bytes: *const u8, tx_len: u32, height: *mut u32) -> bool;
+        pub(crate) fn check_coinbase_height(
+            tx_bytes: *const u8,
+            tx_len: u32,
+            height: u32,
+            network: Network,
+        ) -> u32;
+        pub(crate) fn get_block_subsidy(height: u32, network: Network) -> i64;
+        pub(crate) fn check_coinbase_value(
+            block_bytes: *const u8,
+            block_len: u32,
+            input_values: *const i64,
+            input_values_len: u32,
+            height: u32,
+            network: Network,
+        ) -> u32;
+        pub(crate) fn get_median_time_past(timestamps: *const u32, count: u32) -> i64;
+        pub(crate) fn check_header_time(
+            header_bytes: *const u8,
+            timestamps: *const u32,
+            count: u32,
+            adjusted_time: i64,
+            error: *mut ValidationError,
+        ) -> bool;
     }
 }
 
@@ -33,54 +433,161 @@ pub fn sha256(input: &[u8]) -> [u8; 32] {
     output
 }
 
-pub fn get_block_hash(header: &[u8; 80]) -> Result<[u8; 32]> {
+/// Double SHA-256 of each 64-byte input, the hash of a merkle tree node from its two children.
+///
+/// Hashes several inputs at once where the CPU supports it, which is much faster than calling
+/// [`sha256`] twice per input.
+pub fn sha256d_64(inputs: &[[u8; 64]]) -> Vec<[u8; 32]> {
+    let mut output = vec![[0u8; 32]; inputs.len()];
+    unsafe {
+        ffi::sha256d_64(
+            inputs.as_ptr().cast(),
+            inputs.len() as u32,
+            output.as_mut_ptr().cast(),
+        );
+    }
+    output
+}
+
+pub fn get_block_hash(header: &[u8; 80]) -> Result<[u8; 32], BitcoinError> {
     let mut hash = [0u8; 32];
     let success = unsafe { ffi::get_header_hash(header.as_ptr(), hash.as_mut_ptr()) };
     if success {
         Ok(hash)
     } else {
-        Err(BitcoinError::BlockHashError.into())
+        Err(BitcoinError::HeaderDeserialize)
     }
 }
 
-pub fn check_proof_of_work(header: &[u8; 80]) -> bool {
-    unsafe { ffi::check_proof_of_work(header.as_ptr()) }
+pub fn check_proof_of_work(header: &[u8; 80], network: Network) -> bool {
+    unsafe { ffi::check_proof_of_work(header.as_ptr(), network) }
 }
 
-pub fn get_retarget_height(height: u32) -> u32 {
-    unsafe { ffi::get_retarget_height(height) }
+pub fn get_retarget_height(height: u32, network: Network) -> u32 {
+    unsafe { ffi::get_retarget_height(height, network) }
 }
 
+/// Cheap sanity check that a block at `height` with nBits `new_bits` may follow a parent with
+/// `old_bits`, as done by Bitcoin Core's headers presync (`PermittedDifficultyTransition`).
+///
+/// Outside a retarget the nBits must be unchanged, at a retarget they may move by at most a
+/// factor of four. Always true on networks allowing min-difficulty blocks. Passing this does not
+/// make the nBits valid, use [`validate_next_work_required`] for that.
+pub fn permitted_difficulty_transition(
+    height: u32,
+    old_bits: u32,
+    new_bits: u32,
+    network: Network,
+) -> bool {
+    unsafe { ffi::permitted_difficulty_transition(height, old_bits, new_bits, network) }
+}
+
+/// Checks the nBits of `current_header` and returns the retarget header of the chain it extends.
+///
+/// On networks allowing min-difficulty blocks, a block following a min-difficulty block that
+/// isn't at a retarget height takes the nBits of the last block before it, which this function
+/// can't see. Such headers are rejected with [`BitcoinError::LastNonMinDifficultyBitsRequired`];
+/// use [`validate_next_work_required_with_last_bits`] there.
 pub fn validate_next_work_required(
     last_retarget_header: &[u8; 80],
     previous_height: u32,
     previous_header: &[u8; 80],
     current_header: &[u8; 80],
-) -> Result<[u8; 80]> {
+    network: Network,
+) -> Result<[u8; 80], BitcoinError> {
+    next_work_required(
+        last_retarget_header,
+        previous_height,
+        previous_header,
+        current_header,
+        None,
+        network,
+    )
+}
+
+/// Like [`validate_next_work_required`], with the caller tracking the nBits the testnet
+/// min-difficulty rule falls back to instead of walking back through the block index.
+///
+/// `last_non_min_difficulty_bits` is the nBits of the newest block up to `previous_header` that
+/// is at a retarget height or not at the proof of work limit. Keep it up to date with
+/// [`get_last_non_min_difficulty_bits`] as headers are accepted.
+pub fn validate_next_work_required_with_last_bits(
+    last_retarget_header: &[u8; 80],
+    previous_height: u32,
+    previous_header: &[u8; 80],
+    current_header: &[u8; 80],
+    last_non_min_difficulty_bits: u32,
+    network: Network,
+) -> Result<[u8; 80], BitcoinError> {
+    next_work_required(
+        last_retarget_header,
+        previous_height,
+        previous_header,
+        current_header,
+        Some(last_non_min_difficulty_bits),
+        network,
+    )
+}
+
+fn next_work_required(
+    last_retarget_header: &[u8; 80],
+    previous_height: u32,
+    previous_header: &[u8; 80],
+    current_header: &[u8; 80],
+    last_non_min_difficulty_bits: Option<u32>,
+    network: Network,
+) -> Result<[u8; 80], BitcoinError> {
+    let last_non_min_difficulty_bits = last_non_min_difficulty_bits
+        .as_ref()
+        .map_or(core::ptr::null(), |bits| bits as *const u32);
     let mut next_retarget_header = [0u8; 80];
+    let mut error = ffi::ValidationError::default();
     let success = unsafe {
         ffi::validate_next_work_required(
             last_retarget_header.as_ptr(),
             previous_height,
             previous_header.as_ptr(),
             current_header.as_ptr(),
+            last_non_min_difficulty_bits,
+            network,
             next_retarget_header.as_mut_ptr(),
+            &mut error,
         )
     };
     if success {
         Ok(next_retarget_header)
     } else {
-        Err(BitcoinError::WorkRequirementError.into())
+        Err(BitcoinError::from_validation_error(&error))
     }
 }
 
-pub fn get_block_proof(header: &[u8; 80]) -> Result<[u8; 32]> {
+/// Returns the `last_non_min_difficulty_bits` for a chain extended by a block at `height` with
+/// nBits `header_bits`, given the value for the chain up to its parent.
+///
+/// Min-difficulty blocks leave it unchanged unless they start a difficulty adjustment period.
+pub fn get_last_non_min_difficulty_bits(
+    last_non_min_difficulty_bits: u32,
+    height: u32,
+    header_bits: u32,
+    network: Network,
+) -> u32 {
+    unsafe {
+        ffi::get_last_non_min_difficulty_bits(
+            last_non_min_difficulty_bits,
+            height,
+            header_bits,
+            network,
+        )
+    }
+}
+
+pub fn get_block_proof(header: &[u8; 80]) -> Result<[u8; 32], BitcoinError> {
     let mut proof = [0u8; 32];
     let success = unsafe { ffi::get_block_proof(header.as_ptr(), proof.as_mut_ptr()) };
     if success {
         Ok(proof)
     } else {
-        Err(BitcoinError::DeserializeError.into())
+        Err(BitcoinError::HeaderDeserialize)
     }
 }
 
@@ -92,7 +599,6 @@ pub fn check_header_connection(header: &[u8; 80], previous_header: &[u8; 80]) ->
 mod tests {
     use super::*;
     use alloy_primitives::hex;
-    use alloy_primitives::{utils::format_units, U256};
     use std::collections::HashMap;
 
     // Define a HashMap of height to hex values
@@ -125,26 +631,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sha256_million_a() {
+        let hash = sha256(&vec![b'a'; 1_000_000]);
+        assert_eq!(
+            hash,
+            hex!("cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0")
+        );
+    }
+
+    #[test]
+    fn test_sha256d_64() {
+        // enough inputs to go through the 8, 4 and 2 way transforms as well as the single one
+        let inputs: Vec<[u8; 64]> = (0..19u8)
+            .map(|i| core::array::from_fn(|j| i.wrapping_mul(64).wrapping_add(j as u8)))
+            .collect();
+        for count in 0..=inputs.len() {
+            let hashes = sha256d_64(&inputs[..count]);
+            assert_eq!(hashes.len(), count);
+            for (input, hash) in inputs.iter().zip(&hashes) {
+                assert_eq!(*hash, sha256(&sha256(input)));
+            }
+        }
+    }
+
     #[test]
     fn test_check_pow_real_block() {
         // Test case 1: Valid bitcoin block header that meets PoW requirement
         let serialized_header =
             hex!("00606a2a6da096d2b8dbbbed775ac73ebffb4f8005625ff082d902000000000000000000636f25b00a6dba593285caae62bc20cb5c022050efdae664ff52255c1c2e1b754de10867cd0e031739d4a0ef");
-        assert!(check_proof_of_work(&serialized_header));
+        assert!(check_proof_of_work(&serialized_header, Network::Mainnet));
     }
 
     #[test]
     fn test_check_pow_null_header() {
         let serialized_header =
             hex!("0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000");
-        assert!(!check_proof_of_work(&serialized_header));
+        assert!(!check_proof_of_work(&serialized_header, Network::Mainnet));
     }
 
     #[test]
     fn test_check_pow_fake_block() {
         let serialized_header =
             hex!("010000006024f927c294aafe77f7eff56d0d35e9309dc6a5595b54ffa79200000000000002d8003f9c8c10750d7cb64d3e9cd36cdfc3f0b20db3afd1f25b3657002515a5fa71b04dacb5001ba2e71604");
-        assert!(!check_proof_of_work(&serialized_header));
+        assert!(!check_proof_of_work(&serialized_header, Network::Mainnet));
     }
 
     #[test]
@@ -159,18 +689,39 @@ mod tests {
 
     #[test]
     fn test_get_retarget_height() {
-        assert_eq!(get_retarget_height(0), 0);
-        assert_eq!(get_retarget_height(2015), 0);
-        assert_eq!(get_retarget_height(2016), 0);
-        assert_eq!(get_retarget_height(2017), 2016);
+        assert_eq!(get_retarget_height(0, Network::Mainnet), 0);
+        assert_eq!(get_retarget_height(2015, Network::Mainnet), 0);
+        assert_eq!(get_retarget_height(2016, Network::Mainnet), 0);
+        assert_eq!(get_retarget_height(2017, Network::Mainnet), 2016);
 
-        assert_eq!(get_retarget_height(4031), 2016);
-        assert_eq!(get_retarget_height(4032), 2016);
-        assert_eq!(get_retarget_height(4033), 4032);
+        assert_eq!(get_retarget_height(4031, Network::Mainnet), 2016);
+        assert_eq!(get_retarget_height(4032, Network::Mainnet), 2016);
+        assert_eq!(get_retarget_height(4033, Network::Mainnet), 4032);
 
-        assert_eq!(get_retarget_height(40319), 38304);
-        assert_eq!(get_retarget_height(40320), 38304);
-        assert_eq!(get_retarget_height(40321), 40320);
+        assert_eq!(get_retarget_height(40319, Network::Mainnet), 38304);
+        assert_eq!(get_retarget_height(40320, Network::Mainnet), 38304);
+        assert_eq!(get_retarget_height(40321, Network::Mainnet), 40320);
+    }
+
+    #[test]
+    fn test_check_pow_network_pow_limit() {
+        // regtest genesis block, mined against regtest's 0x207fffff powLimit
+        let regtest_genesis = hex!("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff7f2002000000");
+        assert!(check_proof_of_work(&regtest_genesis, Network::Regtest));
+        assert!(!check_proof_of_work(&regtest_genesis, Network::Mainnet));
+
+        // signet genesis block, mined against signet's 0x1e0377ae powLimit
+        let signet_genesis = hex!("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a008f4d5fae77031e8ad22203");
+        assert!(check_proof_of_work(&signet_genesis, Network::Signet));
+        assert!(!check_proof_of_work(&signet_genesis, Network::Mainnet));
+    }
+
+    #[test]
+    fn test_get_retarget_height_regtest() {
+        // regtest retargets every 144 blocks (one day)
+        assert_eq!(get_retarget_height(144, Network::Regtest), 0);
+        assert_eq!(get_retarget_height(145, Network::Regtest), 144);
+        assert_eq!(get_retarget_height(2017, Network::Regtest), 2016);
     }
 
     #[test]
@@ -188,6 +739,7 @@ mod tests {
             previous_height,
             previous_header,
             new_header,
+            Network::Mainnet,
         )
         .unwrap();
 
@@ -210,6 +762,7 @@ mod tests {
             previous_height,
             previous_header,
             new_header,
+            Network::Mainnet,
         )
         .unwrap();
 
@@ -232,12 +785,87 @@ mod tests {
             previous_height,
             previous_header,
             new_header,
+            Network::Mainnet,
         )
         .unwrap();
 
         assert_eq!(result, *last_retarget_header);
     }
 
+    #[test]
+    fn test_get_next_work_required_bad_diffbits() {
+        let headers = get_headers();
+        let last_retarget_header = *headers.get(&38304).unwrap();
+        let previous_header = *headers.get(&40319).unwrap();
+        // 40320 carrying the nBits of the previous period instead of the retargeted ones
+        let mut new_header = **headers.get(&40320).unwrap();
+        new_header[72..76].copy_from_slice(&previous_header[72..76]);
+
+        let result = validate_next_work_required(
+            last_retarget_header,
+            40319,
+            previous_header,
+            &new_header,
+            Network::Mainnet,
+        );
+
+        assert_eq!(
+            result,
+            Err(BitcoinError::Header(HeaderRejection::BadDiffBits {
+                expected: 0x1c654657,
+                actual: 0x1d008cc3
+            }))
+        );
+    }
+
+    // Heights and headers that used to reach the asserts of GetNextWorkRequiredNoIndex
+    // must come back as errors instead of aborting the process
+    #[test]
+    fn test_get_next_work_required_adversarial() {
+        let headers = get_headers();
+        let header = *headers.get(&40320).unwrap();
+        for height in [
+            i32::MAX as u32,
+            i32::MAX as u32 + 2015,
+            u32::MAX - 1,
+            u32::MAX,
+        ] {
+            for network in [Network::Mainnet, Network::Testnet3, Network::Signet] {
+                assert_eq!(
+                    validate_next_work_required(header, height, header, header, network),
+                    Err(BitcoinError::Header(HeaderRejection::HeightOutOfRange))
+                );
+            }
+        }
+
+        // Nothing here is a valid chain, but none of it may abort either
+        let garbage = [[0u8; 80], [0xff; 80], *header];
+        let networks = [
+            Network::Mainnet,
+            Network::Testnet3,
+            Network::Testnet4,
+            Network::Signet,
+            Network::Regtest,
+        ];
+        for height in [0, 1, 143, 144, 2014, 2015, 2016, i32::MAX as u32 - 1] {
+            for network in networks {
+                for last_retarget in &garbage {
+                    for previous in &garbage {
+                        for current in &garbage {
+                            let _ = validate_next_work_required(
+                                last_retarget,
+                                height,
+                                previous,
+                                current,
+                                network,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     // this test is useful to make sure that we're retargeting to new headers
     // even if a difficulty adjustment doesn't occur
     #[test]
@@ -247,7 +875,7 @@ mod tests {
         let next_header = hex!("01000000e6bf7fd7f7790a63786faa878d0dc7fd8f2ff365732e45862c66075100000000700d342f65c7b6834dffb615358a1897016f0448913372190cbe3d27a4b53355b1512b4bffff001dbfb02519");
         let previous_retarget_height = 28224;
         assert_eq!(
-            get_retarget_height(previous_height),
+            get_retarget_height(previous_height, Network::Mainnet),
             previous_retarget_height
         );
         let last_retarget_header = hex!("010000000bc8739494b0c7c5a575a09b33af1e44fd6eaf71064e8c8900a7f1f900000000374f258b62986d40f1596532151567e3084ab391177e7676ab046e6e2b7cd594cb49104bffff001ddc4fd604");
@@ -257,6 +885,7 @@ mod tests {
             previous_height,
             &previous_header,
             &next_header,
+            Network::Mainnet,
         )
         .unwrap();
         assert_ne!(
@@ -265,6 +894,302 @@ mod tests {
         );
     }
 
+    // A testnet4 period whose first block was mined at real difficulty and whose last block
+    // used the 20 minute min-difficulty exception. The headers only carry what the nBits
+    // calculation reads, they are not part of the actual testnet4 chain
+    fn testnet4_period(timespan: u32) -> (BlockHeader, BlockHeader, BlockHeader) {
+        let first = BlockHeader {
+            version: 0x20000000,
+            prev_blockhash: [0x11; 32],
+            merkle_root: [0x22; 32],
+            time: 1_716_000_000,
+            bits: 0x1c2a1115,
+            nonce: 0,
+        };
+        let last = BlockHeader {
+            prev_blockhash: [0x33; 32],
+            time: first.time + timespan,
+            bits: 0x1d00ffff,
+            ..first
+        };
+        let next = BlockHeader {
+            prev_blockhash: last.hash(),
+            time: last.time + 600,
+            ..first
+        };
+        (first, last, next)
+    }
+
+    #[test]
+    fn test_get_next_work_required_bip94() {
+        // 2016 blocks in exactly two weeks, so the difficulty of the first block carries over
+        let (first, last, next) = testnet4_period(2016 * 600);
+        let validate = |next: &BlockHeader, network| {
+            validate_next_work_required(
+                &first.to_bytes(),
+                4031,
+                &last.to_bytes(),
+                &next.to_bytes(),
+                network,
+            )
+        };
+        assert_eq!(validate(&next, Network::Testnet4), Ok(next.to_bytes()));
+        // testnet3 retargets from the min-difficulty nBits of the last block instead
+        assert_eq!(
+            validate(&next, Network::Testnet3),
+            Err(BitcoinError::Header(HeaderRejection::BadDiffBits {
+                expected: 0x1d00ffff,
+                actual: 0x1c2a1115
+            }))
+        );
+
+        // blocks twice as fast halve the target of the first block
+        let (first, last, next) = testnet4_period(1008 * 600);
+        let next = BlockHeader {
+            bits: 0x1c15088a,
+            ..next
+        };
+        assert_eq!(
+            validate_next_work_required(
+                &first.to_bytes(),
+                4031,
+                &last.to_bytes(),
+                &next.to_bytes(),
+                Network::Testnet4,
+            ),
+            Ok(next.to_bytes())
+        );
+    }
+
+    #[test]
+    fn test_get_next_work_required_timewarp() {
+        let (first, last, next) = testnet4_period(2016 * 600);
+        let validate = |time: u32, bits: u32, previous_height: u32, network| {
+            let next = BlockHeader { time, bits, ..next };
+            validate_next_work_required_with_last_bits(
+                &first.to_bytes(),
+                previous_height,
+                &last.to_bytes(),
+                &next.to_bytes(),
+                last.bits,
+                network,
+            )
+        };
+
+        // the first block of a period may be at most MAX_TIMEWARP seconds older than its parent
+        assert!(validate(last.time - 600, next.bits, 4031, Network::Testnet4).is_ok());
+        assert_eq!(
+            validate(last.time - 601, next.bits, 4031, Network::Testnet4),
+            Err(BitcoinError::Header(HeaderRejection::TimeTimewarpAttack))
+        );
+
+        // the rule only applies at retargets, and only where BIP94 is enforced
+        assert!(validate(last.time - 601, last.bits, 4030, Network::Testnet4).is_ok());
+        assert!(validate(last.time - 601, last.bits, 4031, Network::Testnet3).is_ok());
+    }
+
+    #[test]
+    fn test_testnet4_genesis() {
+        // the real testnet4 genesis header, the first block of its first difficulty period
+        let genesis = hex!("0100000000000000000000000000000000000000000000000000000000000000000000004e7b2b9128fe0291db0693af2ae418b767e657cd407e80cb1434221eaea7a07a046f3566ffff001dbb0c7817");
+        let mut hash = get_block_hash(&genesis).unwrap();
+        hash.reverse();
+        assert_eq!(
+            hash,
+            hex!("00000000da84f2bafbbc53dee25a72ae507ff4914b867c565be350b0da8bf043")
+        );
+        assert!(check_proof_of_work(&genesis, Network::Testnet4));
+
+        // blocks twice as fast as targeted through the first period halve the genesis target
+        let first = BlockHeader::from_bytes(&genesis);
+        let (_, last, next) = testnet4_period(1008 * 600);
+        let last = BlockHeader {
+            time: first.time + 1008 * 600,
+            ..last
+        };
+        let next = BlockHeader {
+            time: last.time + 600,
+            bits: 0x1c7fff80,
+            ..next
+        };
+        assert_eq!(
+            validate_next_work_required(
+                &genesis,
+                2015,
+                &last.to_bytes(),
+                &next.to_bytes(),
+                Network::Testnet4,
+            ),
+            Ok(next.to_bytes())
+        );
+    }
+
+    #[test]
+    fn test_unknown_validation_error() {
+        // codes missing from the Rust table are reported instead of panicking
+        for code in [0, 7, 12, u32::MAX] {
+            let error = ffi::ValidationError {
+                code,
+                ..Default::default()
+            };
+            assert_eq!(
+                BitcoinError::from_validation_error(&error),
+                BitcoinError::Header(HeaderRejection::Unknown(code))
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_next_work_required_min_difficulty() {
+        // Synthetic testnet3 blocks 4033 to 4035, the middle one mined more than
+        // 20 minutes after its parent at the proof of work limit
+        let real = BlockHeader {
+            version: 0x20000000,
+            prev_blockhash: [0x11; 32],
+            merkle_root: [0x22; 32],
+            time: 1_716_000_000,
+            bits: 0x1c2a1115,
+            nonce: 0,
+        };
+        let min_difficulty = BlockHeader {
+            prev_blockhash: real.hash(),
+            time: real.time + 1201,
+            bits: 0x1d00ffff,
+            ..real
+        };
+        let next = BlockHeader {
+            prev_blockhash: min_difficulty.hash(),
+            time: min_difficulty.time + 600,
+            ..real
+        };
+        let validate = |previous: &BlockHeader, header: &BlockHeader, height: u32, last_bits| {
+            validate_next_work_required_with_last_bits(
+                &[0u8; 80],
+                height - 1,
+                &previous.to_bytes(),
+                &header.to_bytes(),
+                last_bits,
+                Network::Testnet3,
+            )
+        };
+
+        let last_bits =
+            get_last_non_min_difficulty_bits(0x1c2a1115, 4033, real.bits, Network::Testnet3);
+        assert!(validate(&real, &min_difficulty, 4034, last_bits).is_ok());
+        let last_bits = get_last_non_min_difficulty_bits(
+            last_bits,
+            4034,
+            min_difficulty.bits,
+            Network::Testnet3,
+        );
+        assert_eq!(last_bits, real.bits);
+        assert!(validate(&min_difficulty, &next, 4035, last_bits).is_ok());
+        assert_eq!(
+            validate(&min_difficulty, &next, 4035, min_difficulty.bits),
+            Err(BitcoinError::Header(HeaderRejection::BadDiffBits {
+                expected: 0x1d00ffff,
+                actual: 0x1c2a1115
+            }))
+        );
+
+        // without the tracked nBits, only headers that don't need them can be checked
+        let validate_untracked = |previous: &BlockHeader, header: &BlockHeader, height: u32| {
+            validate_next_work_required(
+                &[0u8; 80],
+                height - 1,
+                &previous.to_bytes(),
+                &header.to_bytes(),
+                Network::Testnet3,
+            )
+        };
+        assert!(validate_untracked(&real, &min_difficulty, 4034).is_ok());
+        assert_eq!(
+            validate_untracked(&min_difficulty, &next, 4035),
+            Err(BitcoinError::LastNonMinDifficultyBitsRequired)
+        );
+        let late = BlockHeader {
+            time: min_difficulty.time + 1201,
+            bits: 0x1d00ffff,
+            ..next
+        };
+        assert!(validate_untracked(&min_difficulty, &late, 4035).is_ok());
+
+        // a min-difficulty block starting a period is where the walk back stops
+        assert_eq!(
+            get_last_non_min_difficulty_bits(real.bits, 4032, 0x1d00ffff, Network::Testnet3),
+            0x1d00ffff
+        );
+    }
+
+    #[test]
+    fn test_permitted_difficulty_transition() {
+        // 40319 to 40320 on mainnet
+        assert!(permitted_difficulty_transition(
+            40320,
+            0x1d008cc3,
+            0x1c654657,
+            Network::Mainnet
+        ));
+        assert!(permitted_difficulty_transition(
+            40321,
+            0x1c654657,
+            0x1c654657,
+            Network::Mainnet
+        ));
+        assert!(!permitted_difficulty_transition(
+            40321,
+            0x1c654657,
+            0x1c654658,
+            Network::Mainnet
+        ));
+
+        // at most a factor of four either way, capped at the proof of work limit
+        assert!(permitted_difficulty_transition(
+            40320,
+            0x1c200000,
+            0x1d008000,
+            Network::Mainnet
+        ));
+        assert!(!permitted_difficulty_transition(
+            40320,
+            0x1c200000,
+            0x1d008001,
+            Network::Mainnet
+        ));
+        assert!(permitted_difficulty_transition(
+            40320,
+            0x1c200000,
+            0x1c080000,
+            Network::Mainnet
+        ));
+        assert!(!permitted_difficulty_transition(
+            40320,
+            0x1c200000,
+            0x1c07ffff,
+            Network::Mainnet
+        ));
+        assert!(permitted_difficulty_transition(
+            40320,
+            0x1d00ffff,
+            0x1d00ffff,
+            Network::Mainnet
+        ));
+        assert!(!permitted_difficulty_transition(
+            40320,
+            0x1d00ffff,
+            0x1d01fffe,
+            Network::Mainnet
+        ));
+
+        // min-difficulty blocks make any transition possible
+        assert!(permitted_difficulty_transition(
+            40321,
+            0x1c654657,
+            0x1d00ffff,
+            Network::Testnet3
+        ));
+    }
+
     #[test]
     fn test_get_block_proof_genesis_block() {
         let mut expected_proof =
@@ -289,8 +1214,9 @@ mod tests {
         let header = hex!("010000001a231097b6ab6279c80f24674a2c8ee5b9a848e1d45715ad89b6358100000000a822bafe6ed8600e3ffce6d61d10df1927eafe9bbf677cb44c4d209f143c6ba8db8c784b5746651cce222118");
         let proof = get_block_proof(&header).unwrap();
 
-        let calculated_chainwork: [u8; 32] =
-            (U256::from_le_bytes(previous_header_proof) + U256::from_le_bytes(proof)).to_le_bytes();
+        let calculated_chainwork: [u8; 32] = (ChainWork::from_le_bytes(previous_header_proof)
+            + ChainWork::from_le_bytes(proof))
+        .to_le_bytes();
 
         assert_eq!(calculated_chainwork, expected_proof);
     }
@@ -0,0 +1,292 @@
+use alloc::vec::Vec;
+
+use crate::error::BitcoinError;
+use crate::ffi;
+use crate::transaction::Transaction;
+
+/// Maximum merkle branch length, the depth of a tree with 2^32 leaves.
+pub const MAX_MERKLE_BRANCH_LENGTH: usize = 32;
+
+/// Computes the merkle root of `txids` (internal byte order) like `ComputeMerkleRoot`.
+///
+/// The returned flag is set if the tree contains two identical adjacent subtrees,
+/// which lets a block with duplicated transactions share the merkle root of the
+/// valid block (CVE-2012-2459). A block whose root is mutated must be rejected.
+pub fn compute_merkle_root(txids: &[[u8; 32]]) -> ([u8; 32], bool) {
+    let mut root = [0u8; 32];
+    let mut mutated = false;
+    unsafe {
+        ffi::compute_merkle_root(
+            txids.as_ptr() as *const u8,
+            txids.len() as u32,
+            root.as_mut_ptr(),
+            &mut mutated,
+        );
+    }
+    (root, mutated)
+}
+
+/// Deserializes a block and computes the merkle root of its txids, see [`compute_merkle_root`].
+pub fn block_merkle_root(block: &[u8]) -> Result<([u8; 32], bool), BitcoinError> {
+    let mut root = [0u8; 32];
+    let mut mutated = false;
+    let success = unsafe {
+        ffi::block_merkle_root(
+            block.as_ptr(),
+            block.len() as u32,
+            root.as_mut_ptr(),
+            &mut mutated,
+        )
+    };
+    if success {
+        Ok((root, mutated))
+    } else {
+        Err(BitcoinError::BlockDeserialize)
+    }
+}
+
+/// Deserializes a block and computes the merkle root of its wtxids, with the
+/// coinbase's wtxid taken as zero. This is the root a segwit coinbase commits to.
+pub fn block_witness_merkle_root(block: &[u8]) -> Result<([u8; 32], bool), BitcoinError> {
+    let mut root = [0u8; 32];
+    let mut mutated = false;
+    let success = unsafe {
+        ffi::block_witness_merkle_root(
+            block.as_ptr(),
+            block.len() as u32,
+            root.as_mut_ptr(),
+            &mut mutated,
+        )
+    };
+    if success {
+        Ok((root, mutated))
+    } else {
+        Err(BitcoinError::BlockDeserialize)
+    }
+}
+
+/// Computes the branch proving that `txids[index]` is committed to by [`compute_merkle_root`].
+///
+/// Returns `None` if `index` is out of range.
+pub fn merkle_branch(txids: &[[u8; 32]], index: u32) -> Option<Vec<[u8; 32]>> {
+    let mut branch = [[0u8; 32]; MAX_MERKLE_BRANCH_LENGTH];
+    let mut branch_len = 0u32;
+    let success = unsafe {
+        ffi::compute_merkle_branch(
+            txids.as_ptr() as *const u8,
+            txids.len() as u32,
+            index,
+            branch.as_mut_ptr() as *mut u8,
+            &mut branch_len,
+        )
+    };
+    success.then(|| branch[..branch_len as usize].to_vec())
+}
+
+/// Verifies that `branch` proves `txid` is at `index` in a block with the given `merkle_root`.
+///
+/// The branch must fit a tree as deep as it is long: `index` must be within it, and only the
+/// last node of a level may be paired with itself. The depth of the tree isn't known from the
+/// root alone, so a 64-byte internal node can still be passed off as a transaction; use
+/// [`verify_merkle_branch_with_coinbase`] to rule that out.
+pub fn verify_merkle_branch(
+    txid: &[u8; 32],
+    branch: &[[u8; 32]],
+    index: u32,
+    merkle_root: &[u8; 32],
+) -> bool {
+    unsafe {
+        ffi::verify_merkle_branch(
+            txid.as_ptr(),
+            branch.as_ptr() as *const u8,
+            branch.len() as u32,
+            index,
+            merkle_root.as_ptr(),
+        )
+    }
+}
+
+/// Like [`verify_merkle_branch`], additionally requiring `branch` to be as deep as the tree.
+///
+/// The depth is taken from `coinbase_branch`, which must prove that `coinbase` is the first
+/// transaction of the block. No internal node hashes to a null prevout, so only a real
+/// coinbase can have a valid branch at index 0, and every leaf of the tree is equally deep.
+pub fn verify_merkle_branch_with_coinbase(
+    txid: &[u8; 32],
+    branch: &[[u8; 32]],
+    index: u32,
+    coinbase: &Transaction,
+    coinbase_branch: &[[u8; 32]],
+    merkle_root: &[u8; 32],
+) -> bool {
+    coinbase.is_coinbase()
+        && branch.len() == coinbase_branch.len()
+        && verify_merkle_branch(&coinbase.txid(), coinbase_branch, 0, merkle_root)
+        && verify_merkle_branch(txid, branch, index, merkle_root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::BlockHeader;
+    use alloy_primitives::hex;
+
+    const GENESIS_BLOCK: [u8; 285] = hex!("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000");
+
+    #[test]
+    fn test_compute_merkle_root() {
+        let txids: Vec<[u8; 32]> = (1..=5).map(|i| [i; 32]).collect();
+
+        assert_eq!(compute_merkle_root(&[]), ([0u8; 32], false));
+        assert_eq!(compute_merkle_root(&txids[..1]), (txids[0], false));
+        assert_eq!(
+            compute_merkle_root(&txids[..2]),
+            (
+                hex!("39ce20bede82c96b8908bec4a157b09c549b3db90b9b474bda9ae9b9030310b4"),
+                false
+            )
+        );
+        assert_eq!(
+            compute_merkle_root(&txids),
+            (
+                hex!("26e2870f72368b3f8baef83fa26282d95d9c194e1f33d90a12932e0f6022e5d3"),
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn test_compute_merkle_root_mutated() {
+        let txids: Vec<[u8; 32]> = (1..=3).map(|i| [i; 32]).collect();
+        let (root, mutated) = compute_merkle_root(&txids);
+        assert!(!mutated);
+
+        // duplicating the last txid yields the same root, but is flagged
+        let duplicated = [txids[0], txids[1], txids[2], txids[2]];
+        assert_eq!(compute_merkle_root(&duplicated), (root, true));
+    }
+
+    #[test]
+    fn test_block_merkle_root_genesis() {
+        let header = BlockHeader::from_bytes(GENESIS_BLOCK[..80].try_into().unwrap());
+        assert_eq!(
+            block_merkle_root(&GENESIS_BLOCK).unwrap(),
+            (header.merkle_root, false)
+        );
+        // the coinbase wtxid is replaced by zero
+        assert_eq!(
+            block_witness_merkle_root(&GENESIS_BLOCK).unwrap(),
+            ([0u8; 32], false)
+        );
+    }
+
+    #[test]
+    fn test_block_merkle_root_malformed() {
+        assert!(block_merkle_root(&GENESIS_BLOCK[..200]).is_err());
+        assert!(block_merkle_root(&[GENESIS_BLOCK.as_slice(), &[0]].concat()).is_err());
+        // a header followed by an empty transaction list has no coinbase
+        let mut empty_block = GENESIS_BLOCK[..81].to_vec();
+        empty_block[80] = 0;
+        assert_eq!(block_merkle_root(&empty_block).unwrap(), ([0u8; 32], false));
+        assert!(block_witness_merkle_root(&empty_block).is_err());
+    }
+
+    #[test]
+    fn test_merkle_branch_round_trip() {
+        for count in 1..=9u8 {
+            let txids: Vec<[u8; 32]> = (1..=count).map(|i| [i; 32]).collect();
+            let (root, _) = compute_merkle_root(&txids);
+            for index in 0..count as u32 {
+                let branch = merkle_branch(&txids, index).unwrap();
+                assert!(verify_merkle_branch(
+                    &txids[index as usize],
+                    &branch,
+                    index,
+                    &root
+                ));
+                assert!(!verify_merkle_branch(&[0xff; 32], &branch, index, &root));
+            }
+            assert!(merkle_branch(&txids, count as u32).is_none());
+        }
+    }
+
+    #[test]
+    fn test_verify_merkle_branch_rejects_internal_node() {
+        let coinbase = Transaction::from_bytes(&GENESIS_BLOCK[81..]).unwrap();
+        let mut txids: Vec<[u8; 32]> = (2..=4).map(|i| [i; 32]).collect();
+        txids.insert(0, coinbase.txid());
+        let (root, _) = compute_merkle_root(&txids);
+        let coinbase_branch = merkle_branch(&txids, 0).unwrap();
+        let branch = merkle_branch(&txids, 2).unwrap();
+        assert!(verify_merkle_branch_with_coinbase(
+            &txids[2],
+            &branch,
+            2,
+            &coinbase,
+            &coinbase_branch,
+            &root
+        ));
+
+        // the parent of txids 2 and 3 is the double SHA256 of a 64-byte "transaction",
+        // which only the depth of the coinbase branch rejects as a leaf
+        let (internal_node, _) = compute_merkle_root(&txids[2..]);
+        assert!(verify_merkle_branch(&internal_node, &branch[1..], 1, &root));
+        assert!(!verify_merkle_branch_with_coinbase(
+            &internal_node,
+            &branch[1..],
+            1,
+            &coinbase,
+            &coinbase_branch,
+            &root
+        ));
+
+        // nor can a branch be extended below the leaves
+        let extended = [&[[0xaa; 32]], branch.as_slice()].concat();
+        assert!(!verify_merkle_branch_with_coinbase(
+            &[0xbb; 32],
+            &extended,
+            4,
+            &coinbase,
+            &coinbase_branch,
+            &root
+        ));
+
+        // and the depth must come from a real coinbase
+        let mut spend = GENESIS_BLOCK[81..].to_vec();
+        spend[5] = 1;
+        let not_coinbase = Transaction::from_bytes(&spend).unwrap();
+        assert!(!verify_merkle_branch_with_coinbase(
+            &txids[2],
+            &branch,
+            2,
+            &not_coinbase,
+            &coinbase_branch,
+            &root
+        ));
+    }
+
+    #[test]
+    fn test_verify_merkle_branch_duplicates() {
+        let txids: Vec<[u8; 32]> = (1..=3).map(|i| [i; 32]).collect();
+        let (root, _) = compute_merkle_root(&txids);
+
+        // the odd last leaf is paired with itself, at index 3 it would be a mutated duplicate
+        let branch = merkle_branch(&txids, 2).unwrap();
+        assert_eq!(branch[0], txids[2]);
+        assert!(verify_merkle_branch(&txids[2], &branch, 2, &root));
+        assert!(!verify_merkle_branch(&txids[2], &branch, 3, &root));
+        // an index deeper than the branch doesn't fit the tree
+        assert!(!verify_merkle_branch(&txids[2], &branch, 6, &root));
+
+        // once paired with itself a node stays the last of its level
+        let txids: Vec<[u8; 32]> = (1..=5).map(|i| [i; 32]).collect();
+        let (root, _) = compute_merkle_root(&txids);
+        let branch = merkle_branch(&txids, 4).unwrap();
+        assert!(verify_merkle_branch(&txids[4], &branch, 4, &root));
+        let forged = [txids[4], [0xcc; 32], branch[2]];
+        let (parent, _) = compute_merkle_root(&[txids[4], txids[4]]);
+        let (parent, _) = compute_merkle_root(&[parent, forged[1]]);
+        let (forged_root, _) = compute_merkle_root(&[forged[2], parent]);
+        assert!(!verify_merkle_branch(&txids[4], &forged, 4, &forged_root));
+    }
+}
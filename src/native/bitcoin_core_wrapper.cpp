@@ -5,15 +5,40 @@
 #include "vendor/bitcoin/src/primitives/block.h"
 #include "vendor/bitcoin/src/chain.h"
 #include "vendor/bitcoin/src/kernel/chainparams.h"
+#include "vendor/bitcoin/src/consensus/consensus.h"
+#include "vendor/bitcoin/src/consensus/merkle.h"
+#include "vendor/bitcoin/src/consensus/tx_check.h"
+#include "vendor/bitcoin/src/consensus/validation.h"
+#include "vendor/bitcoin/src/hash.h"
+#include "vendor/bitcoin/src/pubkey.h"
+#include "vendor/bitcoin/src/script/interpreter.h"
 
+#include <limits>
 #include <memory>
+#include <optional>
+#include <string>
+#include <string_view>
 #include <vector>
 
 static const size_t HEADER_LENGTH = 80;
 
+#if defined(__x86_64__) || defined(__amd64__) || defined(__aarch64__)
+// Switches CSHA256 and SHA256D64 to the fastest implementation the CPU supports when the library
+// is loaded, the same selection bitcoind makes at startup. Not needed inside the zkVM, where the
+// transform is fixed at build time
+static const std::string SHA256_IMPLEMENTATION = SHA256AutoDetect();
+#endif
+
+// The flags the former libbitcoinconsensus accepted (bitcoinconsensus_SCRIPT_FLAGS_VERIFY_ALL)
+static const unsigned int SCRIPT_VERIFY_LIBCONSENSUS_ALL =
+    SCRIPT_VERIFY_P2SH | SCRIPT_VERIFY_DERSIG | SCRIPT_VERIFY_NULLDUMMY |
+    SCRIPT_VERIFY_CHECKLOCKTIMEVERIFY | SCRIPT_VERIFY_CHECKSEQUENCEVERIFY |
+    SCRIPT_VERIFY_WITNESS | SCRIPT_VERIFY_TAPROOT;
+
 // The config initializers in bitcoin core create global state,
-// so we need to duplicate the relevant ones in this pure initializer to avoid that
-static const Consensus::Params get_consensus_params()
+// so we need to duplicate the relevant ones in this pure initializer to avoid that.
+// Values mirror the CChainParams subclasses in kernel/chainparams.cpp
+static const Consensus::Params get_consensus_params(const Network network)
 {
     Consensus::Params consensus;
     consensus.signet_blocks = false;
@@ -25,13 +50,64 @@ static const Consensus::Params get_consensus_params()
     consensus.fPowAllowMinDifficultyBlocks = false;
     consensus.enforce_BIP94 = false;
     consensus.fPowNoRetargeting = false;
+
+    switch (network)
+    {
+    case NETWORK_MAINNET:
+        consensus.BIP34Height = 227931;
+        consensus.BIP65Height = 388381;
+        consensus.BIP66Height = 363725;
+        consensus.CSVHeight = 419328;
+        consensus.SegwitHeight = 481824;
+        break;
+    case NETWORK_TESTNET3:
+        consensus.BIP34Height = 21111;
+        consensus.BIP65Height = 581885;
+        consensus.BIP66Height = 330776;
+        consensus.CSVHeight = 770112;
+        consensus.SegwitHeight = 834624;
+        consensus.fPowAllowMinDifficultyBlocks = true;
+        break;
+    case NETWORK_TESTNET4:
+        consensus.BIP34Height = 1;
+        consensus.BIP65Height = 1;
+        consensus.BIP66Height = 1;
+        consensus.CSVHeight = 1;
+        consensus.SegwitHeight = 1;
+        consensus.fPowAllowMinDifficultyBlocks = true;
+        consensus.enforce_BIP94 = true;
+        break;
+    case NETWORK_SIGNET:
+        // The signet challenge is only needed to validate block solutions, which
+        // this library doesn't do, so it is left empty
+        consensus.signet_blocks = true;
+        consensus.BIP34Height = 1;
+        consensus.BIP65Height = 1;
+        consensus.BIP66Height = 1;
+        consensus.CSVHeight = 1;
+        consensus.SegwitHeight = 1;
+        consensus.powLimit = uint256::FromHex("00000377ae000000000000000000000000000000000000000000000000000000").value();
+        break;
+    case NETWORK_REGTEST:
+        consensus.nSubsidyHalvingInterval = 150;
+        consensus.BIP34Height = 1;
+        consensus.BIP65Height = 1;
+        consensus.BIP66Height = 1;
+        consensus.CSVHeight = 1;
+        consensus.SegwitHeight = 0;
+        consensus.powLimit = uint256::FromHex("7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").value();
+        consensus.nPowTargetTimespan = 24 * 60 * 60; // one day
+        consensus.fPowAllowMinDifficultyBlocks = true;
+        consensus.enforce_BIP94 = true;
+        consensus.fPowNoRetargeting = true;
+        break;
+    }
     return consensus;
 }
 
 static bool deserialize_header(const unsigned char *header_bytes, CBlockHeader &header)
 {
-    std::vector<unsigned char> serialized_header(header_bytes, header_bytes + HEADER_LENGTH);
-    DataStream ser_header{serialized_header};
+    SpanReader ser_header{Span<const unsigned char>(header_bytes, HEADER_LENGTH)};
     try
     {
         ser_header >> header;
@@ -43,11 +119,485 @@ static bool deserialize_header(const unsigned char *header_bytes, CBlockHeader &
     }
 }
 
-// Simple fork of GetNextWorkRequired that doesn't require an index, so we don't have to build a skip list
-static uint32_t GetNextWorkRequiredNoIndex(const CBlockIndex *pindexLast, const CBlockHeader *pblock, const CBlockIndex *pretarget, const Consensus::Params &params)
+// Deserializes a block with witness data, rejecting trailing bytes
+static bool deserialize_block(const unsigned char *block_bytes, const uint32_t block_len, CBlock &block)
+{
+    DataStream ser_block{Span<const unsigned char>(block_bytes, block_len)};
+    try
+    {
+        ser_block >> TX_WITH_WITNESS(block);
+        return ser_block.empty();
+    }
+    catch (const std::exception &)
+    {
+        return false;
+    }
+}
+
+// Deserializes a transaction with witness data, rejecting trailing bytes
+static bool deserialize_transaction(const unsigned char *tx_bytes, const uint32_t tx_len, CMutableTransaction &tx)
+{
+    DataStream ser_tx{Span<const unsigned char>(tx_bytes, tx_len)};
+    try
+    {
+        ser_tx >> TX_WITH_WITNESS(tx);
+        return ser_tx.empty();
+    }
+    catch (const std::exception &)
+    {
+        return false;
+    }
+}
+
+struct TransactionHandle
+{
+    CTransaction tx;
+};
+
+// Cursor over a serialized transaction for transaction_decode_error
+struct TransactionReader
+{
+    const unsigned char *bytes;
+    size_t len;
+    size_t pos;
+};
+
+static uint32_t skip(TransactionReader &reader, const uint64_t count)
+{
+    if (count > reader.len - reader.pos)
+    {
+        return TX_END_OF_DATA;
+    }
+    reader.pos += count;
+    return TX_OK;
+}
+
+static uint64_t read_le(const unsigned char *bytes, const size_t count)
+{
+    uint64_t value = 0;
+    for (size_t i = 0; i < count; i++)
+    {
+        value |= uint64_t{bytes[i]} << (8 * i);
+    }
+    return value;
+}
+
+// ReadCompactSize with range_check
+static uint32_t read_compact_size(TransactionReader &reader, uint64_t &size)
+{
+    const size_t start = reader.pos;
+    if (skip(reader, 1) != TX_OK)
+    {
+        return TX_END_OF_DATA;
+    }
+    const unsigned char prefix = reader.bytes[start];
+    const size_t width = prefix < 253 ? 0 : prefix == 253 ? 2 : prefix == 254 ? 4 : 8;
+    if (skip(reader, width) != TX_OK)
+    {
+        return TX_END_OF_DATA;
+    }
+    size = width == 0 ? prefix : read_le(reader.bytes + start + 1, width);
+    if ((width == 2 && size < 253) || (width == 4 && size < 0x10000u) || (width == 8 && size < 0x100000000ULL))
+    {
+        return TX_NON_CANONICAL_COMPACT_SIZE;
+    }
+    return size > MAX_SIZE ? TX_SIZE_TOO_LARGE : TX_OK;
+}
+
+// A script or a witness item
+static uint32_t skip_byte_vector(TransactionReader &reader)
+{
+    uint64_t size;
+    const uint32_t result = read_compact_size(reader, size);
+    return result != TX_OK ? result : skip(reader, size);
+}
+
+static uint32_t skip_inputs(TransactionReader &reader, uint64_t &count)
+{
+    uint32_t result = read_compact_size(reader, count);
+    for (uint64_t i = 0; result == TX_OK && i < count; i++)
+    {
+        result = skip(reader, 36);
+        if (result == TX_OK)
+        {
+            result = skip_byte_vector(reader);
+        }
+        if (result == TX_OK)
+        {
+            result = skip(reader, 4);
+        }
+    }
+    return result;
+}
+
+static uint32_t skip_outputs(TransactionReader &reader)
+{
+    uint64_t count;
+    uint32_t result = read_compact_size(reader, count);
+    for (uint64_t i = 0; result == TX_OK && i < count; i++)
+    {
+        result = skip(reader, 8);
+        if (result == TX_OK)
+        {
+            result = skip_byte_vector(reader);
+        }
+    }
+    return result;
+}
+
+// Tells why deserializing a transaction failed by repeating the reads of UnserializeTransaction
+// without throwing. The messages of the exceptions Core throws aren't part of its interface,
+// so they aren't relied on
+static uint32_t transaction_decode_error(const unsigned char *tx_bytes, const uint32_t tx_len)
+{
+    TransactionReader reader{tx_bytes, tx_len, 0};
+    uint64_t input_count;
+    uint32_t result = skip(reader, 4);
+    if (result == TX_OK)
+    {
+        result = skip_inputs(reader, input_count);
+    }
+    if (result != TX_OK)
+    {
+        return result;
+    }
+
+    unsigned char flags = 0;
+    if (input_count == 0)
+    {
+        // A dummy empty vin followed by the segwit flags
+        if (skip(reader, 1) != TX_OK)
+        {
+            return TX_END_OF_DATA;
+        }
+        flags = tx_bytes[reader.pos - 1];
+        if (flags != 0)
+        {
+            result = skip_inputs(reader, input_count);
+            if (result == TX_OK)
+            {
+                result = skip_outputs(reader);
+            }
+        }
+    }
+    else
+    {
+        result = skip_outputs(reader);
+    }
+    if (result != TX_OK)
+    {
+        return result;
+    }
+
+    if (flags & 1)
+    {
+        flags ^= 1;
+        bool has_witness = false;
+        for (uint64_t i = 0; i < input_count; i++)
+        {
+            uint64_t item_count;
+            result = read_compact_size(reader, item_count);
+            for (uint64_t j = 0; result == TX_OK && j < item_count; j++)
+            {
+                result = skip_byte_vector(reader);
+            }
+            if (result != TX_OK)
+            {
+                return result;
+            }
+            has_witness |= item_count != 0;
+        }
+        if (!has_witness)
+        {
+            return TX_SUPERFLUOUS_WITNESS_RECORD;
+        }
+    }
+    if (flags)
+    {
+        return TX_UNKNOWN_OPTIONAL_DATA;
+    }
+    if (skip(reader, 4) != TX_OK)
+    {
+        return TX_END_OF_DATA;
+    }
+    // Deserialization threw on input this walk accepts, e.g. a new rule in a vendored Core bump
+    return TX_OTHER;
+}
+
+static uint32_t block_reject_code(const std::string &reason)
+{
+    static const std::pair<std::string_view, uint32_t> REJECT_REASONS[] = {
+        {"high-hash", BLOCK_HIGH_HASH},
+        {"bad-txnmrklroot", BLOCK_BAD_TXNMRKLROOT},
+        {"bad-txns-duplicate", BLOCK_BAD_TXNS_DUPLICATE},
+        {"bad-blk-length", BLOCK_BAD_BLK_LENGTH},
+        {"bad-cb-missing", BLOCK_BAD_CB_MISSING},
+        {"bad-cb-multiple", BLOCK_BAD_CB_MULTIPLE},
+        {"bad-txns-vin-empty", BLOCK_BAD_TXNS_VIN_EMPTY},
+        {"bad-txns-vout-empty", BLOCK_BAD_TXNS_VOUT_EMPTY},
+        {"bad-txns-oversize", BLOCK_BAD_TXNS_OVERSIZE},
+        {"bad-txns-vout-negative", BLOCK_BAD_TXNS_VOUT_NEGATIVE},
+        {"bad-txns-vout-toolarge", BLOCK_BAD_TXNS_VOUT_TOOLARGE},
+        {"bad-txns-txouttotal-toolarge", BLOCK_BAD_TXNS_TXOUTTOTAL_TOOLARGE},
+        {"bad-txns-inputs-duplicate", BLOCK_BAD_TXNS_INPUTS_DUPLICATE},
+        {"bad-cb-length", BLOCK_BAD_CB_LENGTH},
+        {"bad-txns-prevout-null", BLOCK_BAD_TXNS_PREVOUT_NULL},
+        {"bad-blk-sigops", BLOCK_BAD_BLK_SIGOPS},
+        {"bad-witness-nonce-size", BLOCK_BAD_WITNESS_NONCE_SIZE},
+        {"bad-witness-merkle-match", BLOCK_BAD_WITNESS_MERKLE_MATCH},
+        {"unexpected-witness", BLOCK_UNEXPECTED_WITNESS},
+        {"bad-blk-weight", BLOCK_BAD_BLK_WEIGHT},
+        {"bad-cb-amount", BLOCK_BAD_CB_AMOUNT},
+        {"bad-txns-inputvalues-outofrange", BLOCK_BAD_TXNS_INPUTVALUES_OUTOFRANGE},
+        {"bad-txns-in-belowout", BLOCK_BAD_TXNS_IN_BELOWOUT},
+        {"bad-txns-fee-outofrange", BLOCK_BAD_TXNS_FEE_OUTOFRANGE},
+        {"bad-txns-accumulated-fee-outofrange", BLOCK_BAD_TXNS_ACCUMULATED_FEE_OUTOFRANGE},
+    };
+    for (const auto &[reject_reason, code] : REJECT_REASONS)
+    {
+        if (reason == reject_reason)
+            return code;
+    }
+    return BLOCK_OTHER;
+}
+
+// GetLegacySigOpCount from consensus/tx_verify.cpp, which can't be linked without the UTXO set code
+static unsigned int GetLegacySigOpCount(const CTransaction &tx)
+{
+    unsigned int nSigOps = 0;
+    for (const auto &txin : tx.vin)
+    {
+        nSigOps += txin.scriptSig.GetSigOpCount(false);
+    }
+    for (const auto &txout : tx.vout)
+    {
+        nSigOps += txout.scriptPubKey.GetSigOpCount(false);
+    }
+    return nSigOps;
+}
+
+// CheckBlock from validation.cpp with fCheckPOW and fCheckMerkleRoot set, without the signet
+// block solution check, which needs the signet challenge
+static bool CheckBlock(const CBlock &block, BlockValidationState &state, const Consensus::Params &consensusParams)
+{
+    // Check that the header is valid (particularly PoW)
+    if (!CheckProofOfWork(block.GetHash(), block.nBits, consensusParams))
+        return state.Invalid(BlockValidationResult::BLOCK_INVALID_HEADER, "high-hash", "proof of work failed");
+
+    // Check the merkle root.
+    bool mutated;
+    uint256 merkle_root = BlockMerkleRoot(block, &mutated);
+    if (block.hashMerkleRoot != merkle_root)
+        return state.Invalid(BlockValidationResult::BLOCK_MUTATED, "bad-txnmrklroot", "hashMerkleRoot mismatch");
+
+    // Check for merkle tree malleability (CVE-2012-2459): repeating sequences
+    // of transactions in a block without affecting the merkle root of a block,
+    // while still invalidating it.
+    if (mutated)
+        return state.Invalid(BlockValidationResult::BLOCK_MUTATED, "bad-txns-duplicate", "duplicate transaction");
+
+    // Size limits
+    if (block.vtx.empty() || block.vtx.size() * WITNESS_SCALE_FACTOR > MAX_BLOCK_WEIGHT || ::GetSerializeSize(TX_NO_WITNESS(block)) * WITNESS_SCALE_FACTOR > MAX_BLOCK_WEIGHT)
+        return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, "bad-blk-length", "size limits failed");
+
+    // First transaction must be coinbase, the rest must not be
+    if (block.vtx.empty() || !block.vtx[0]->IsCoinBase())
+        return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, "bad-cb-missing", "first tx is not coinbase");
+    for (unsigned int i = 1; i < block.vtx.size(); i++)
+        if (block.vtx[i]->IsCoinBase())
+            return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, "bad-cb-multiple", "more than one coinbase");
+
+    // Check transactions
+    // Must check for duplicate inputs (see CVE-2018-17144)
+    for (const auto &tx : block.vtx)
+    {
+        TxValidationState tx_state;
+        if (!CheckTransaction(*tx, tx_state))
+        {
+            return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, tx_state.GetRejectReason(), tx_state.GetDebugMessage());
+        }
+    }
+    unsigned int nSigOps = 0;
+    for (const auto &tx : block.vtx)
+    {
+        nSigOps += GetLegacySigOpCount(*tx);
+    }
+    if (nSigOps * WITNESS_SCALE_FACTOR > MAX_BLOCK_SIGOPS_COST)
+        return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, "bad-blk-sigops", "out-of-bounds SigOpCount");
+
+    return true;
+}
+
+// The witness reserved value of a coinbase, which must be its only witness item
+static const std::vector<unsigned char> *witness_reserved_value(const CTransaction &coinbase)
+{
+    const auto &witness_stack{coinbase.vin[0].scriptWitness.stack};
+    if (witness_stack.size() != 1 || witness_stack[0].size() != 32)
+    {
+        return nullptr;
+    }
+    return &witness_stack[0];
+}
+
+// CheckWitnessMalleation from validation.cpp with expect_witness_commitment set, followed by the
+// block weight check ContextualCheckBlock does once the commitment is verified
+static bool CheckWitnessCommitment(const CBlock &block, BlockValidationState &state)
+{
+    int commitpos = GetWitnessCommitmentIndex(block);
+    if (commitpos != NO_WITNESS_COMMITMENT)
+    {
+        const std::vector<unsigned char> *reserved_value = witness_reserved_value(*block.vtx[0]);
+        if (reserved_value == nullptr)
+            return state.Invalid(BlockValidationResult::BLOCK_MUTATED, "bad-witness-nonce-size", "invalid witness reserved value size");
+
+        // The malleation check is ignored; as the transaction tree itself
+        // already does not permit it, it is impossible to trigger in the
+        // witness tree.
+        uint256 hash_witness = BlockWitnessMerkleRoot(block, /*mutated=*/nullptr);
+
+        CHash256().Write(hash_witness).Write(*reserved_value).Finalize(hash_witness);
+        if (memcmp(hash_witness.begin(), &block.vtx[0]->vout[commitpos].scriptPubKey[6], 32))
+            return state.Invalid(BlockValidationResult::BLOCK_MUTATED, "bad-witness-merkle-match", "witness merkle commitment mismatch");
+    }
+    else
+    {
+        // No witness data is allowed in blocks that don't commit to witness data, as this would otherwise leave room for spam
+        for (const auto &tx : block.vtx)
+        {
+            if (tx->HasWitness())
+                return state.Invalid(BlockValidationResult::BLOCK_MUTATED, "unexpected-witness", "unexpected witness data found");
+        }
+    }
+
+    if (GetBlockWeight(block) > MAX_BLOCK_WEIGHT)
+        return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, "bad-blk-weight", "weight limit failed");
+
+    return true;
+}
+
+// The serialized height BIP34 requires a coinbase scriptSig to start with, see ContextualCheckBlock
+static CScript coinbase_height_prefix(const int64_t height)
+{
+    return CScript() << height;
+}
+
+// GetBlockSubsidy from validation.cpp, taking the height unsigned. Every network is past its 64th
+// halving long before INT_MAX, from where on the subsidy is zero
+static CAmount GetBlockSubsidy(const uint32_t height, const Consensus::Params &consensusParams)
+{
+    if (height > static_cast<uint32_t>(std::numeric_limits<int>::max()))
+        return 0;
+
+    int halvings = static_cast<int>(height) / consensusParams.nSubsidyHalvingInterval;
+    // Force block reward to zero when right shift is undefined.
+    if (halvings >= 64)
+        return 0;
+
+    CAmount nSubsidy = 50 * COIN;
+    // Subsidy is cut in half every 210,000 blocks which will occur approximately every 4 years.
+    nSubsidy >>= halvings;
+    return nSubsidy;
+}
+
+// The fee tally of ConnectBlock and Consensus::CheckTxInputs followed by the coinbase value check,
+// with input_values holding the values of the spent coins in block order
+static bool CheckCoinbaseValue(const CBlock &block, Span<const CAmount> input_values, const uint32_t height, const Consensus::Params &params, BlockValidationState &state)
+{
+    // GetValueOut throws on output values CheckTransaction rejects
+    for (const auto &tx : block.vtx)
+    {
+        TxValidationState tx_state;
+        if (!CheckTransaction(*tx, tx_state))
+            return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, tx_state.GetRejectReason(), tx_state.GetDebugMessage());
+    }
+
+    CAmount nFees = 0;
+    size_t input = 0;
+    for (unsigned int i = 1; i < block.vtx.size(); i++)
+    {
+        const CTransaction &tx = *block.vtx[i];
+        CAmount nValueIn = 0;
+        for (unsigned int j = 0; j < tx.vin.size(); j++)
+        {
+            // Check for negative or overflow input values. Unlike coins from the UTXO set, the
+            // values are checked before being added so untrusted ones can't overflow the sum
+            const CAmount value = input_values[input++];
+            if (!MoneyRange(value))
+                return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, "bad-txns-inputvalues-outofrange");
+            nValueIn += value;
+            if (!MoneyRange(nValueIn))
+                return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, "bad-txns-inputvalues-outofrange");
+        }
+
+        const CAmount value_out = tx.GetValueOut();
+        if (nValueIn < value_out)
+            return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, "bad-txns-in-belowout");
+
+        // Tally transaction fees
+        const CAmount txfee = nValueIn - value_out;
+        if (!MoneyRange(txfee))
+            return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, "bad-txns-fee-outofrange");
+
+        nFees += txfee;
+        if (!MoneyRange(nFees))
+            return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, "bad-txns-accumulated-fee-outofrange");
+    }
+
+    CAmount blockReward = nFees + GetBlockSubsidy(height, params);
+    if (block.vtx[0]->GetValueOut() > blockReward)
+        return state.Invalid(BlockValidationResult::BLOCK_CONSENSUS, "bad-cb-amount");
+
+    return true;
+}
+
+// Fork of CalculateNextWorkRequired taking the first block of the period instead of its time. The
+// BIP94 branch looks that block up through GetAncestor, which walks pprev and would dereference
+// null on our standalone indexes
+static unsigned int CalculateNextWorkRequiredNoIndex(const CBlockIndex *pindexLast, const CBlockIndex *pindexFirst, const Consensus::Params &params)
+{
+    if (params.fPowNoRetargeting)
+        return pindexLast->nBits;
+
+    // Limit adjustment step
+    int64_t nActualTimespan = pindexLast->GetBlockTime() - pindexFirst->GetBlockTime();
+    if (nActualTimespan < params.nPowTargetTimespan / 4)
+        nActualTimespan = params.nPowTargetTimespan / 4;
+    if (nActualTimespan > params.nPowTargetTimespan * 4)
+        nActualTimespan = params.nPowTargetTimespan * 4;
+
+    // Retarget
+    const arith_uint256 bnPowLimit = UintToArith256(params.powLimit);
+    arith_uint256 bnNew;
+
+    // Special difficulty rule for Testnet4
+    if (params.enforce_BIP94)
+    {
+        // Here we use the first block of the difficulty period. This way
+        // the real difficulty is always preserved in the first block as
+        // it is not allowed to use the min-difficulty exception.
+        bnNew.SetCompact(pindexFirst->nBits);
+    }
+    else
+    {
+        bnNew.SetCompact(pindexLast->nBits);
+    }
+
+    bnNew *= nActualTimespan;
+    bnNew /= params.nPowTargetTimespan;
+
+    if (bnNew > bnPowLimit)
+        bnNew = bnPowLimit;
+
+    return bnNew.GetCompact();
+}
+
+// Simple fork of GetNextWorkRequired that doesn't require an index, so we don't have to build a skip list.
+// Returns nullopt where GetNextWorkRequired would assert, as heights come from untrusted callers
+// The testnet min-difficulty walk-back over pprev is replaced by last_non_min_difficulty_bits, the nBits
+// of the newest block up to pindexLast that is at a retarget height or not at the proof of work limit
+static std::optional<uint32_t> GetNextWorkRequiredNoIndex(const CBlockIndex *pindexLast, const CBlockHeader *pblock, const CBlockIndex *pretarget,
+                                                          const uint32_t last_non_min_difficulty_bits, const Consensus::Params &params)
 {
-    assert(pindexLast != nullptr);
-    assert(pretarget != nullptr);
     unsigned int nProofOfWorkLimit = UintToArith256(params.powLimit).GetCompact();
 
     // Only change once per difficulty adjustment interval
@@ -63,10 +613,7 @@ static uint32_t GetNextWorkRequiredNoIndex(const CBlockIndex *pindexLast, const
             else
             {
                 // Return the last non-special-min-difficulty-rules-block
-                const CBlockIndex *pindex = pindexLast;
-                while (pindex->pprev && pindex->nHeight % params.DifficultyAdjustmentInterval() != 0 && pindex->nBits == nProofOfWorkLimit)
-                    pindex = pindex->pprev;
-                return pindex->nBits;
+                return last_non_min_difficulty_bits;
             }
         }
         return pindexLast->nBits;
@@ -74,10 +621,110 @@ static uint32_t GetNextWorkRequiredNoIndex(const CBlockIndex *pindexLast, const
 
     // Go back by what we want to be 14 days worth of blocks
     int nHeightFirst = pindexLast->nHeight - (params.DifficultyAdjustmentInterval() - 1);
-    assert(nHeightFirst >= 0);
-    assert(pretarget->nHeight == nHeightFirst);
+    if (nHeightFirst < 0 || pretarget->nHeight != nHeightFirst)
+    {
+        return std::nullopt;
+    }
+
+    return CalculateNextWorkRequiredNoIndex(pindexLast, pretarget, params);
+}
+
+// The last_non_min_difficulty_bits of a chain extended by a block at height with header_bits,
+// tracked in place of the ancestors GetNextWorkRequired walks back through on testnet
+static uint32_t next_last_non_min_difficulty_bits(const uint32_t last_non_min_difficulty_bits, const uint32_t height,
+                                                  const uint32_t header_bits, const Consensus::Params &params)
+{
+    if (height % params.DifficultyAdjustmentInterval() == 0 || header_bits != UintToArith256(params.powLimit).GetCompact())
+    {
+        return header_bits;
+    }
+    return last_non_min_difficulty_bits;
+}
+
+// Whether GetNextWorkRequired would walk back past pindexLast, a min-difficulty block, to find the nBits
+// of the header, so they can't be computed from pindexLast alone
+static bool needs_last_non_min_difficulty_bits(const CBlockIndex &previous_index, const CBlockHeader &header, const Consensus::Params &params)
+{
+    return params.fPowAllowMinDifficultyBlocks &&
+           (previous_index.nHeight + 1) % params.DifficultyAdjustmentInterval() != 0 &&
+           header.GetBlockTime() <= previous_index.GetBlockTime() + params.nPowTargetSpacing * 2 &&
+           previous_index.nHeight % params.DifficultyAdjustmentInterval() != 0 &&
+           previous_index.nBits == UintToArith256(params.powLimit).GetCompact();
+}
+
+static uint32_t retarget_height(const uint32_t height, const Consensus::Params &params)
+{
+    if (height < params.DifficultyAdjustmentInterval())
+    {
+        return 0;
+    }
+    return ((height - 1) / params.DifficultyAdjustmentInterval()) * params.DifficultyAdjustmentInterval();
+}
+
+// CBlockIndex::nHeight is an int, so the height of the previous block and of the block
+// being validated must both fit
+static bool is_valid_previous_height(const uint32_t previous_height)
+{
+    return previous_height < static_cast<uint32_t>(std::numeric_limits<int>::max());
+}
+
+// Testnet4 and regtest only: Check timestamp against prev for difficulty-adjustment
+// blocks to prevent timewarp attacks (see https://github.com/bitcoin/bitcoin/pull/15482).
+static bool is_timewarp(const CBlockHeader &header, const CBlockHeader &previous_header, const uint32_t height, const Consensus::Params &params)
+{
+    return params.enforce_BIP94 && height % params.DifficultyAdjustmentInterval() == 0 &&
+           header.GetBlockTime() < previous_header.GetBlockTime() - MAX_TIMEWARP;
+}
+
+// Median of the given timestamps (newest first), computed by CBlockIndex::GetMedianTimePast
+// over a throwaway pprev chain so the result matches Core exactly
+static int64_t median_time_past(const uint32_t *timestamps, const size_t count)
+{
+    CBlockIndex indexes[CBlockIndex::nMedianTimeSpan];
+    for (size_t i = 0; i < count; i++)
+    {
+        indexes[i].nTime = timestamps[i];
+        indexes[i].pprev = i + 1 < count ? &indexes[i + 1] : nullptr;
+    }
+    return indexes[0].GetMedianTimePast();
+}
+
+// Fills in the reason a header was rejected, returning false so callers can return it directly
+static bool reject(ValidationError *error, const uint32_t code)
+{
+    error->code = code;
+    error->expected_bits = 0;
+    error->actual_bits = 0;
+    return false;
+}
+
+static bool reject_bad_diffbits(ValidationError *error, const uint32_t expected_bits, const uint32_t actual_bits)
+{
+    reject(error, HEADER_BAD_DIFFBITS);
+    error->expected_bits = expected_bits;
+    error->actual_bits = actual_bits;
+    return false;
+}
+
+// The timestamp checks of ContextualCheckBlockHeader
+static uint32_t check_time(const CBlockHeader &header, const CBlockHeader &previous_header, const uint32_t height,
+                           const int64_t median_time_past, const int64_t adjusted_time, const Consensus::Params &params)
+{
+    if (header.GetBlockTime() <= median_time_past)
+    {
+        return HEADER_TIME_TOO_OLD;
+    }
+
+    if (is_timewarp(header, previous_header, height, params))
+    {
+        return HEADER_TIME_TIMEWARP_ATTACK;
+    }
 
-    return CalculateNextWorkRequired(pindexLast, pretarget->GetBlockTime(), params);
+    if (header.GetBlockTime() > adjusted_time + MAX_FUTURE_BLOCK_TIME)
+    {
+        return HEADER_TIME_TOO_NEW;
+    }
+    return HEADER_VALID;
 }
 
 extern "C" void sha256_hash(const unsigned char *input, const uint32_t input_len, unsigned char hash_result[32])
@@ -87,6 +734,11 @@ extern "C" void sha256_hash(const unsigned char *input, const uint32_t input_len
     sha256.Finalize(hash_result);
 }
 
+extern "C" void sha256d_64(const unsigned char *input, const uint32_t blocks, unsigned char *output)
+{
+    SHA256D64(output, input, blocks);
+}
+
 extern "C" bool get_header_hash(const unsigned char *header_bytes, unsigned char *block_hash)
 {
     CBlockHeader header;
@@ -99,9 +751,9 @@ extern "C" bool get_header_hash(const unsigned char *header_bytes, unsigned char
     return true;
 }
 
-extern "C" bool check_proof_of_work(const unsigned char *header_bytes)
+extern "C" bool check_proof_of_work(const unsigned char *header_bytes, const Network network)
 {
-    const Consensus::Params &params = get_consensus_params();
+    const Consensus::Params &params = get_consensus_params(network);
     CBlockHeader header;
     if (!deserialize_header(header_bytes, header))
     {
@@ -110,37 +762,126 @@ extern "C" bool check_proof_of_work(const unsigned char *header_bytes)
     return CheckProofOfWork(header.GetHash(), header.nBits, params);
 }
 
-extern "C" uint32_t get_retarget_height(const uint32_t height)
+extern "C" bool check_proof_of_work_with_limit(const unsigned char *header_bytes, const unsigned char *pow_limit)
 {
-    const Consensus::Params &params = get_consensus_params();
-    if (height < params.DifficultyAdjustmentInterval())
+    CBlockHeader header;
+    if (!deserialize_header(header_bytes, header))
     {
-        return 0;
+        return false;
     }
-    return ((height - 1) / params.DifficultyAdjustmentInterval()) * params.DifficultyAdjustmentInterval();
+    // CheckProofOfWork only reads powLimit from the parameters
+    Consensus::Params params;
+    params.powLimit = uint256(Span<const unsigned char>(pow_limit, 32));
+    return CheckProofOfWork(header.GetHash(), header.nBits, params);
 }
 
-extern "C" bool get_block_proof(const unsigned char *header_bytes, unsigned char *proof)
+extern "C" bool check_hash_against_target(
+    const unsigned char *header_bytes,
+    const unsigned char *target,
+    const Network network,
+    bool *meets_network_target)
 {
+    *meets_network_target = false;
     CBlockHeader header;
     if (!deserialize_header(header_bytes, header))
     {
         return false;
     }
-    CBlockIndex header_index(header);
-    std::memcpy(proof, ArithToUint256(GetBlockProof(header_index)).data(), 32);
-    return true;
+    const uint256 hash = header.GetHash();
+    *meets_network_target = CheckProofOfWork(hash, header.nBits, get_consensus_params(network));
+
+    // The range and proof checks of CheckProofOfWork, on a target that isn't compact
+    const arith_uint256 bnTarget = UintToArith256(uint256(Span<const unsigned char>(target, 32)));
+    return bnTarget != 0 && UintToArith256(hash) <= bnTarget;
 }
 
-extern "C" bool validate_next_work_required(
-    const unsigned char *last_retarget_header_bytes,
+extern "C" uint32_t get_retarget_height(const uint32_t height, const Network network)
+{
+    return retarget_height(height, get_consensus_params(network));
+}
+
+extern "C" bool permitted_difficulty_transition(
+    const uint32_t height,
+    const uint32_t old_nbits,
+    const uint32_t new_nbits,
+    const Network network)
+{
+    return PermittedDifficultyTransition(get_consensus_params(network), height, old_nbits, new_nbits);
+}
+
+extern "C" bool get_block_proof(const unsigned char *header_bytes, unsigned char *proof)
+{
+    CBlockHeader header;
+    if (!deserialize_header(header_bytes, header))
+    {
+        return false;
+    }
+    CBlockIndex header_index(header);
+    std::memcpy(proof, ArithToUint256(GetBlockProof(header_index)).data(), 32);
+    return true;
+}
+
+extern "C" void target_from_compact(const uint32_t nbits, unsigned char *target, bool *negative, bool *overflow)
+{
+    arith_uint256 value;
+    value.SetCompact(nbits, negative, overflow);
+    std::memcpy(target, ArithToUint256(value).data(), 32);
+}
+
+extern "C" uint32_t target_to_compact(const unsigned char *target)
+{
+    return UintToArith256(uint256(Span<const unsigned char>(target, 32))).GetCompact();
+}
+
+// Fork of GetDifficulty from rpc/blockchain.cpp taking nBits instead of a CBlockIndex
+extern "C" double difficulty_from_compact(const uint32_t nbits)
+{
+    int nShift = (nbits >> 24) & 0xff;
+    double dDiff =
+        (double)0x0000ffff / (double)(nbits & 0x00ffffff);
+
+    while (nShift < 29)
+    {
+        dDiff *= 256.0;
+        nShift++;
+    }
+    while (nShift > 29)
+    {
+        dDiff /= 256.0;
+        nShift--;
+    }
+
+    return dDiff;
+}
+
+// The arithmetic of GetBlockProof on a target that isn't necessarily compact. The largest
+// target is special cased, bnTarget + 1 wraps to zero and arith_uint256 throws on division by it
+extern "C" void target_to_work(const unsigned char *target, unsigned char *work)
+{
+    const arith_uint256 bnTarget = UintToArith256(uint256(Span<const unsigned char>(target, 32)));
+    arith_uint256 proof = 0;
+    if (bnTarget == ~arith_uint256(0))
+    {
+        proof = 1;
+    }
+    else if (bnTarget != 0)
+    {
+        proof = (~bnTarget / (bnTarget + 1)) + 1;
+    }
+    std::memcpy(work, ArithToUint256(proof).data(), 32);
+}
+
+extern "C" bool validate_next_work_required(
+    const unsigned char *last_retarget_header_bytes,
     const uint32_t previous_height,
     const unsigned char *previous_header_bytes,
     const unsigned char *header_bytes,
-    unsigned char *next_retarget_header_bytes)
+    const uint32_t *last_non_min_difficulty_bits,
+    const Network network,
+    unsigned char *next_retarget_header_bytes,
+    ValidationError *error)
 {
-
-    const Consensus::Params &params = get_consensus_params();
+    const Consensus::Params &params = get_consensus_params(network);
     CBlockHeader last_retarget_header;
     CBlockHeader previous_header;
     CBlockHeader header;
@@ -149,7 +890,11 @@ extern "C" bool validate_next_work_required(
         !deserialize_header(previous_header_bytes, previous_header) ||
         !deserialize_header(header_bytes, header))
     {
-        return false;
+        return reject(error, HEADER_DESERIALIZE_FAILED);
+    }
+    if (!is_valid_previous_height(previous_height))
+    {
+        return reject(error, HEADER_BAD_HEIGHT);
     }
 
     // Create previous block index and set its height
@@ -158,14 +903,29 @@ extern "C" bool validate_next_work_required(
 
     // Create the last retarget block index and set its height
     CBlockIndex last_retarget_index(last_retarget_header);
-    last_retarget_index.nHeight = get_retarget_height(previous_height + 1);
+    last_retarget_index.nHeight = retarget_height(previous_height + 1, params);
 
-    // Calculate the next work required for the header
-    uint32_t calculated_next_nbits = GetNextWorkRequiredNoIndex(&previous_index, &header, &last_retarget_index, params);
+    // Without the caller's value, the previous block only stands in for the last non-min-difficulty
+    // block if it is one itself
+    const uint32_t last_bits = last_non_min_difficulty_bits ? *last_non_min_difficulty_bits : previous_header.nBits;
+    if (!last_non_min_difficulty_bits && needs_last_non_min_difficulty_bits(previous_index, header, params))
+    {
+        return reject(error, HEADER_LAST_BITS_REQUIRED);
+    }
 
-    if (header.nBits != calculated_next_nbits)
+    // Calculate the next work required for the header
+    const std::optional<uint32_t> calculated_next_nbits = GetNextWorkRequiredNoIndex(&previous_index, &header, &last_retarget_index, last_bits, params);
+    if (!calculated_next_nbits)
     {
-        return false;
+        return reject(error, HEADER_BAD_HEIGHT);
+    }
+    if (header.nBits != *calculated_next_nbits)
+    {
+        return reject_bad_diffbits(error, *calculated_next_nbits, header.nBits);
+    }
+    if (is_timewarp(header, previous_header, previous_height + 1, params))
+    {
+        return reject(error, HEADER_TIME_TIMEWARP_ATTACK);
     }
 
     // Only update the next retarget header if we're at a retarget boundary
@@ -182,6 +942,15 @@ extern "C" bool validate_next_work_required(
     return true;
 }
 
+extern "C" uint32_t get_last_non_min_difficulty_bits(
+    const uint32_t last_non_min_difficulty_bits,
+    const uint32_t height,
+    const uint32_t header_bits,
+    const Network network)
+{
+    return next_last_non_min_difficulty_bits(last_non_min_difficulty_bits, height, header_bits, get_consensus_params(network));
+}
+
 extern "C" bool check_header_connection(
     const unsigned char *header_bytes,
     const unsigned char *previous_header_bytes)
@@ -195,3 +964,686 @@ extern "C" bool check_header_connection(
     }
     return header.hashPrevBlock == previous_header.GetHash();
 }
+
+
+// accept_block_header with the consensus parameters and the parsed tip and retarget headers
+// already at hand, so validate_header_chain neither rebuilds the former nor parses or hashes
+// every header more than once. On success tip_header, tip_hash and last_retarget_header are
+// advanced to the new tip
+static bool accept_header(
+    const HeaderChainState &state,
+    CBlockHeader &tip_header,
+    uint256 &tip_hash,
+    CBlockHeader &last_retarget_header,
+    const unsigned char *header_bytes,
+    const int64_t adjusted_time,
+    const Consensus::Params &params,
+    HeaderChainState &next_state,
+    ValidationError *error)
+{
+    CBlockHeader header;
+    if (!deserialize_header(header_bytes, header))
+    {
+        return reject(error, HEADER_DESERIALIZE_FAILED);
+    }
+    if (!is_valid_previous_height(state.tip_height))
+    {
+        return reject(error, HEADER_BAD_HEIGHT);
+    }
+    const uint32_t height = state.tip_height + 1;
+
+    // CheckBlockHeader
+    const uint256 header_hash = header.GetHash();
+    if (!CheckProofOfWork(header_hash, header.nBits, params))
+    {
+        return reject(error, HEADER_HIGH_HASH);
+    }
+
+    // AcceptBlockHeader only validates headers whose parent is known
+    if (header.hashPrevBlock != tip_hash)
+    {
+        return reject(error, HEADER_PREV_BLK_NOT_FOUND);
+    }
+
+    // ContextualCheckBlockHeader
+    CBlockIndex tip_index(tip_header);
+    tip_index.nHeight = state.tip_height;
+    CBlockIndex last_retarget_index(last_retarget_header);
+    last_retarget_index.nHeight = retarget_height(height, params);
+
+    const std::optional<uint32_t> expected_bits = GetNextWorkRequiredNoIndex(&tip_index, &header, &last_retarget_index, state.last_non_min_difficulty_bits, params);
+    if (!expected_bits)
+    {
+        return reject(error, HEADER_BAD_HEIGHT);
+    }
+    if (header.nBits != *expected_bits)
+    {
+        return reject_bad_diffbits(error, *expected_bits, header.nBits);
+    }
+
+    const size_t timestamp_count = std::min<size_t>(state.tip_height + 1, MEDIAN_TIME_SPAN);
+    const int64_t mtp = median_time_past(state.timestamps, timestamp_count);
+    const uint32_t time_result = check_time(header, tip_header, height, mtp, adjusted_time, params);
+    if (time_result != HEADER_VALID)
+    {
+        return reject(error, time_result);
+    }
+
+    // Reject blocks with outdated version, DeploymentActiveAfter for buried deployments
+    if ((header.nVersion < 2 && (int)height >= params.DeploymentHeight(Consensus::DEPLOYMENT_HEIGHTINCB)) ||
+        (header.nVersion < 3 && (int)height >= params.DeploymentHeight(Consensus::DEPLOYMENT_DERSIG)) ||
+        (header.nVersion < 4 && (int)height >= params.DeploymentHeight(Consensus::DEPLOYMENT_CLTV)))
+    {
+        return reject(error, HEADER_BAD_VERSION);
+    }
+
+    // Only written once the header is known to be valid, so state and next_state may alias
+    arith_uint256 chainwork = UintToArith256(uint256(Span<const unsigned char>(state.chainwork, 32)));
+    chainwork += GetBlockProof(CBlockIndex(header));
+    uint32_t timestamps[MEDIAN_TIME_SPAN];
+    timestamps[0] = header.nTime;
+    std::memcpy(&timestamps[1], state.timestamps, sizeof(uint32_t) * (MEDIAN_TIME_SPAN - 1));
+
+    if (height % params.DifficultyAdjustmentInterval() == 0)
+    {
+        std::memcpy(next_state.last_retarget_header, header_bytes, HEADER_LENGTH);
+        last_retarget_header = header;
+    }
+    else
+    {
+        std::memmove(next_state.last_retarget_header, state.last_retarget_header, HEADER_LENGTH);
+    }
+    next_state.last_non_min_difficulty_bits = next_last_non_min_difficulty_bits(state.last_non_min_difficulty_bits, height, header.nBits, params);
+    std::memmove(next_state.tip_header, header_bytes, HEADER_LENGTH);
+    next_state.tip_height = height;
+    std::memcpy(next_state.chainwork, ArithToUint256(chainwork).data(), 32);
+    std::memcpy(next_state.timestamps, timestamps, sizeof(timestamps));
+    next_state.network = state.network;
+    tip_header = header;
+    tip_hash = header_hash;
+    return true;
+}
+
+extern "C" bool accept_block_header(
+    const HeaderChainState *state,
+    const unsigned char *header_bytes,
+    const int64_t adjusted_time,
+    HeaderChainState *next_state,
+    ValidationError *error)
+{
+    CBlockHeader tip_header;
+    CBlockHeader last_retarget_header;
+    if (!deserialize_header(state->tip_header, tip_header) ||
+        !deserialize_header(state->last_retarget_header, last_retarget_header))
+    {
+        return reject(error, HEADER_DESERIALIZE_FAILED);
+    }
+    uint256 tip_hash = tip_header.GetHash();
+    return accept_header(*state, tip_header, tip_hash, last_retarget_header, header_bytes, adjusted_time,
+                         get_consensus_params(state->network), *next_state, error);
+}
+
+extern "C" bool validate_header_chain(
+    const HeaderChainState *state,
+    const unsigned char *headers,
+    const uint32_t count,
+    const int64_t adjusted_time,
+    HeaderChainState *end_state,
+    uint32_t *failed_index,
+    ValidationError *error)
+{
+    const Consensus::Params params = get_consensus_params(state->network);
+    CBlockHeader tip_header;
+    CBlockHeader last_retarget_header;
+    if (!deserialize_header(state->tip_header, tip_header) ||
+        !deserialize_header(state->last_retarget_header, last_retarget_header))
+    {
+        *failed_index = 0;
+        return reject(error, HEADER_DESERIALIZE_FAILED);
+    }
+    uint256 tip_hash = tip_header.GetHash();
+
+    // Works on a copy so end_state is left untouched on failure
+    HeaderChainState current = *state;
+    for (uint32_t i = 0; i < count; i++)
+    {
+        if (!accept_header(current, tip_header, tip_hash, last_retarget_header, headers + i * HEADER_LENGTH,
+                           adjusted_time, params, current, error))
+        {
+            *failed_index = i;
+            return false;
+        }
+    }
+    *end_state = current;
+    return true;
+}
+
+extern "C" int64_t get_median_time_past(const uint32_t *timestamps, const uint32_t count)
+{
+    return median_time_past(timestamps, std::min<size_t>(count, MEDIAN_TIME_SPAN));
+}
+
+extern "C" bool check_header_time(
+    const unsigned char *header_bytes,
+    const uint32_t *timestamps,
+    const uint32_t count,
+    const int64_t adjusted_time,
+    ValidationError *error)
+{
+    CBlockHeader header;
+    if (!deserialize_header(header_bytes, header))
+    {
+        return reject(error, HEADER_DESERIALIZE_FAILED);
+    }
+    const int64_t mtp = median_time_past(timestamps, std::min<size_t>(count, MEDIAN_TIME_SPAN));
+    if (header.GetBlockTime() <= mtp)
+    {
+        return reject(error, HEADER_TIME_TOO_OLD);
+    }
+    if (header.GetBlockTime() > adjusted_time + MAX_FUTURE_BLOCK_TIME)
+    {
+        return reject(error, HEADER_TIME_TOO_NEW);
+    }
+    return true;
+}
+
+extern "C" void compute_merkle_root(
+    const unsigned char *hashes,
+    const uint32_t count,
+    unsigned char *root,
+    bool *mutated)
+{
+    std::vector<uint256> leaves;
+    leaves.reserve(count);
+    for (uint32_t i = 0; i < count; i++)
+    {
+        leaves.emplace_back(Span<const unsigned char>(hashes + i * 32, 32));
+    }
+    std::memcpy(root, ComputeMerkleRoot(std::move(leaves), mutated).data(), 32);
+}
+
+extern "C" bool block_merkle_root(
+    const unsigned char *block_bytes,
+    const uint32_t block_len,
+    unsigned char *root,
+    bool *mutated)
+{
+    CBlock block;
+    if (!deserialize_block(block_bytes, block_len, block))
+    {
+        return false;
+    }
+    std::memcpy(root, BlockMerkleRoot(block, mutated).data(), 32);
+    return true;
+}
+
+extern "C" bool block_witness_merkle_root(
+    const unsigned char *block_bytes,
+    const uint32_t block_len,
+    unsigned char *root,
+    bool *mutated)
+{
+    CBlock block;
+    // BlockWitnessMerkleRoot assumes a coinbase is present
+    if (!deserialize_block(block_bytes, block_len, block) || block.vtx.empty())
+    {
+        return false;
+    }
+    std::memcpy(root, BlockWitnessMerkleRoot(block, mutated).data(), 32);
+    return true;
+}
+
+// Same level by level reduction as ComputeMerkleRoot, recording the sibling of position at each level
+extern "C" bool compute_merkle_branch(
+    const unsigned char *hashes,
+    const uint32_t count,
+    const uint32_t position,
+    unsigned char *branch,
+    uint32_t *branch_len)
+{
+    if (position >= count)
+    {
+        return false;
+    }
+    std::vector<uint256> level;
+    level.reserve(count + 1);
+    for (uint32_t i = 0; i < count; i++)
+    {
+        level.emplace_back(Span<const unsigned char>(hashes + i * 32, 32));
+    }
+
+    uint32_t pos = position;
+    uint32_t len = 0;
+    while (level.size() > 1)
+    {
+        if (level.size() & 1)
+        {
+            level.push_back(level.back());
+        }
+        std::memcpy(branch + len * 32, level[pos ^ 1].data(), 32);
+        len++;
+        SHA256D64(level[0].begin(), level[0].begin(), level.size() / 2);
+        level.resize(level.size() / 2);
+        pos >>= 1;
+    }
+    *branch_len = len;
+    return true;
+}
+
+// ComputeMerkleRootFromBranch from test/merkle_tests.cpp, additionally checking that the branch
+// is consistent with the shape of some tree branch_len levels deep
+extern "C" bool verify_merkle_branch(
+    const unsigned char *leaf,
+    const unsigned char *branch,
+    const uint32_t branch_len,
+    const uint32_t position,
+    const unsigned char *root)
+{
+    if (branch_len > MAX_MERKLE_BRANCH_LENGTH ||
+        (branch_len < MAX_MERKLE_BRANCH_LENGTH && (position >> branch_len) != 0))
+    {
+        return false;
+    }
+
+    uint256 hash(Span<const unsigned char>(leaf, 32));
+    uint32_t pos = position;
+    // Whether the node is the last of its level, known once it has been paired with itself
+    bool last = false;
+    for (uint32_t i = 0; i < branch_len; i++)
+    {
+        const uint256 sibling(Span<const unsigned char>(branch + i * 32, 32));
+        // Only the last node of an odd-sized level, a left node, is paired with itself. A left
+        // node that is the last of its level has no other sibling
+        const bool duplicated = sibling == hash;
+        if (duplicated && (pos & 1))
+        {
+            return false;
+        }
+        if (last && !(pos & 1) && !duplicated)
+        {
+            return false;
+        }
+        last = last || duplicated;
+        hash = (pos & 1) ? Hash(sibling, hash) : Hash(hash, sibling);
+        pos >>= 1;
+    }
+    return hash == uint256(Span<const unsigned char>(root, 32));
+}
+
+extern "C" uint32_t transaction_deserialize(
+    const unsigned char *tx_bytes,
+    const uint32_t tx_len,
+    TransactionHandle **handle)
+{
+    DataStream ser_tx{Span<const unsigned char>(tx_bytes, tx_len)};
+    try
+    {
+        CMutableTransaction mtx;
+        ser_tx >> TX_WITH_WITNESS(mtx);
+        if (!ser_tx.empty())
+        {
+            return TX_TRAILING_DATA;
+        }
+        *handle = new TransactionHandle{CTransaction(std::move(mtx))};
+        return TX_OK;
+    }
+    catch (const std::exception &)
+    {
+        return transaction_decode_error(tx_bytes, tx_len);
+    }
+}
+
+extern "C" void transaction_free(TransactionHandle *handle)
+{
+    delete handle;
+}
+
+extern "C" void transaction_info(const TransactionHandle *handle, TransactionInfo *info)
+{
+    const CTransaction &tx = handle->tx;
+    info->version = tx.version;
+    info->lock_time = tx.nLockTime;
+    info->input_count = tx.vin.size();
+    info->output_count = tx.vout.size();
+    std::memcpy(info->txid, tx.GetHash().ToUint256().data(), 32);
+    std::memcpy(info->wtxid, tx.GetWitnessHash().ToUint256().data(), 32);
+    info->weight = GetTransactionWeight(tx);
+}
+
+extern "C" void transaction_input(const TransactionHandle *handle, const uint32_t index, TransactionInputInfo *input)
+{
+    const CTxIn &txin = handle->tx.vin[index];
+    std::memcpy(input->prevout_hash, txin.prevout.hash.ToUint256().data(), 32);
+    input->prevout_n = txin.prevout.n;
+    input->sequence = txin.nSequence;
+    input->script_sig = txin.scriptSig.data();
+    input->script_sig_len = txin.scriptSig.size();
+    input->witness_len = txin.scriptWitness.stack.size();
+}
+
+extern "C" const unsigned char *transaction_input_witness(
+    const TransactionHandle *handle,
+    const uint32_t index,
+    const uint32_t item,
+    uint32_t *item_len)
+{
+    const std::vector<unsigned char> &witness_item = handle->tx.vin[index].scriptWitness.stack[item];
+    *item_len = witness_item.size();
+    return witness_item.data();
+}
+
+extern "C" void transaction_output(const TransactionHandle *handle, const uint32_t index, TransactionOutputInfo *output)
+{
+    const CTxOut &txout = handle->tx.vout[index];
+    output->value = txout.nValue;
+    output->script_pubkey = txout.scriptPubKey.data();
+    output->script_pubkey_len = txout.scriptPubKey.size();
+}
+
+// ScriptError_t is mirrored by `ffi::SCRIPT_ERR_*` in src/lib.rs
+static_assert(SCRIPT_ERR_SIG_FINDANDDELETE == 52 && SCRIPT_ERR_ERROR_COUNT == 53,
+              "ScriptError_t changed, update ffi::SCRIPT_ERR_* in src/lib.rs");
+
+extern "C" uint32_t verify_script(
+    const unsigned char *script_sig,
+    const uint32_t script_sig_len,
+    const unsigned char *script_pubkey,
+    const uint32_t script_pubkey_len,
+    const unsigned char *const *witness_items,
+    const uint32_t *witness_item_lens,
+    const uint32_t witness_len,
+    const uint32_t flags,
+    const unsigned char *tx_bytes,
+    const uint32_t tx_len,
+    const uint32_t input_index,
+    const int64_t amount,
+    const TransactionOutputInfo *spent_outputs,
+    const uint32_t spent_outputs_len,
+    uint32_t *script_error)
+{
+    *script_error = SCRIPT_ERR_UNKNOWN_ERROR;
+    // VerifyScript asserts that WITNESS is only ever used together with P2SH
+    if ((flags & ~SCRIPT_VERIFY_LIBCONSENSUS_ALL) != 0 ||
+        ((flags & SCRIPT_VERIFY_WITNESS) != 0 && (flags & SCRIPT_VERIFY_P2SH) == 0))
+    {
+        return VERIFY_ERR_INVALID_FLAGS;
+    }
+    if ((flags & SCRIPT_VERIFY_TAPROOT) != 0 && spent_outputs == nullptr)
+    {
+        return VERIFY_ERR_SPENT_OUTPUTS_REQUIRED;
+    }
+
+    try
+    {
+        DataStream ser_tx{Span<const unsigned char>(tx_bytes, tx_len)};
+        const CTransaction tx(deserialize, TX_WITH_WITNESS, ser_tx);
+        if (!ser_tx.empty())
+        {
+            return VERIFY_ERR_TX_DESERIALIZE;
+        }
+
+        std::vector<CTxOut> spent;
+        if (spent_outputs != nullptr)
+        {
+            if (spent_outputs_len != tx.vin.size())
+            {
+                return VERIFY_ERR_SPENT_OUTPUTS_MISMATCH;
+            }
+            spent.reserve(spent_outputs_len);
+            for (uint32_t i = 0; i < spent_outputs_len; i++)
+            {
+                const TransactionOutputInfo &output = spent_outputs[i];
+                spent.emplace_back(output.value, CScript(output.script_pubkey, output.script_pubkey + output.script_pubkey_len));
+            }
+        }
+        if (input_index >= tx.vin.size())
+        {
+            return VERIFY_ERR_TX_INDEX;
+        }
+
+        PrecomputedTransactionData txdata(tx);
+        if (spent_outputs != nullptr && (flags & SCRIPT_VERIFY_TAPROOT) != 0)
+        {
+            txdata.Init(tx, std::move(spent));
+        }
+
+        CScriptWitness witness;
+        witness.stack.reserve(witness_len);
+        for (uint32_t i = 0; i < witness_len; i++)
+        {
+            witness.stack.emplace_back(witness_items[i], witness_items[i] + witness_item_lens[i]);
+        }
+
+        ScriptError serror;
+        const bool valid = VerifyScript(
+            CScript(script_sig, script_sig + script_sig_len),
+            CScript(script_pubkey, script_pubkey + script_pubkey_len),
+            &witness,
+            flags,
+            TransactionSignatureChecker(&tx, input_index, amount, txdata, MissingDataBehavior::FAIL),
+            &serror);
+        *script_error = serror;
+        return valid ? VERIFY_OK : VERIFY_ERR_SCRIPT;
+    }
+    catch (const std::exception &)
+    {
+        return VERIFY_ERR_TX_DESERIALIZE;
+    }
+}
+
+extern "C" bool verify_ecdsa(
+    const unsigned char *pubkey,
+    const uint32_t pubkey_len,
+    const unsigned char *sig,
+    const uint32_t sig_len,
+    const unsigned char *msg_hash)
+{
+    const CPubKey key(Span<const unsigned char>(pubkey, pubkey_len));
+    return key.Verify(uint256(Span<const unsigned char>(msg_hash, 32)), std::vector<unsigned char>(sig, sig + sig_len));
+}
+
+extern "C" bool verify_schnorr(const unsigned char *pubkey, const unsigned char *sig, const unsigned char *msg)
+{
+    const XOnlyPubKey key(Span<const unsigned char>(pubkey, 32));
+    return key.VerifySchnorr(uint256(Span<const unsigned char>(msg, 32)), Span<const unsigned char>(sig, 64));
+}
+
+extern "C" uint32_t check_block(const unsigned char *block_bytes, const uint32_t block_len, const Network network)
+{
+    CBlock block;
+    if (!deserialize_block(block_bytes, block_len, block))
+    {
+        return BLOCK_DESERIALIZE_FAILED;
+    }
+    BlockValidationState state;
+    if (!CheckBlock(block, state, get_consensus_params(network)))
+    {
+        return block_reject_code(state.GetRejectReason());
+    }
+    return BLOCK_VALID;
+}
+
+extern "C" int32_t get_witness_commitment_index(const unsigned char *tx_bytes, const uint32_t tx_len)
+{
+    CMutableTransaction mtx;
+    if (!deserialize_transaction(tx_bytes, tx_len, mtx))
+    {
+        return NO_WITNESS_COMMITMENT;
+    }
+    CBlock block;
+    block.vtx.push_back(MakeTransactionRef(std::move(mtx)));
+    return GetWitnessCommitmentIndex(block);
+}
+
+extern "C" uint32_t compute_witness_commitment(
+    const unsigned char *block_bytes,
+    const uint32_t block_len,
+    unsigned char *commitment)
+{
+    CBlock block;
+    if (!deserialize_block(block_bytes, block_len, block))
+    {
+        return BLOCK_DESERIALIZE_FAILED;
+    }
+    if (block.vtx.empty() || !block.vtx[0]->IsCoinBase())
+    {
+        return BLOCK_BAD_CB_MISSING;
+    }
+    const std::vector<unsigned char> *reserved_value = witness_reserved_value(*block.vtx[0]);
+    if (reserved_value == nullptr)
+    {
+        return BLOCK_BAD_WITNESS_NONCE_SIZE;
+    }
+    uint256 hash_witness = BlockWitnessMerkleRoot(block, /*mutated=*/nullptr);
+    CHash256().Write(hash_witness).Write(*reserved_value).Finalize(hash_witness);
+    std::memcpy(commitment, hash_witness.data(), 32);
+    return BLOCK_VALID;
+}
+
+extern "C" uint32_t check_witness_commitment(const unsigned char *block_bytes, const uint32_t block_len)
+{
+    CBlock block;
+    if (!deserialize_block(block_bytes, block_len, block))
+    {
+        return BLOCK_DESERIALIZE_FAILED;
+    }
+    // The commitment is read from the coinbase's first input, so the block must start with one
+    if (block.vtx.empty() || !block.vtx[0]->IsCoinBase())
+    {
+        return BLOCK_BAD_CB_MISSING;
+    }
+    BlockValidationState state;
+    if (!CheckWitnessCommitment(block, state))
+    {
+        return block_reject_code(state.GetRejectReason());
+    }
+    return BLOCK_VALID;
+}
+
+extern "C" bool coinbase_height(const unsigned char *tx_bytes, const uint32_t tx_len, uint32_t *height)
+{
+    CMutableTransaction mtx;
+    if (!deserialize_transaction(tx_bytes, tx_len, mtx))
+    {
+        return false;
+    }
+    const CTransaction tx(std::move(mtx));
+    if (!tx.IsCoinBase())
+    {
+        return false;
+    }
+    const CScript &script_sig = tx.vin[0].scriptSig;
+    CScript::const_iterator pc = script_sig.begin();
+    opcodetype opcode;
+    std::vector<unsigned char> data;
+    if (!script_sig.GetOp(pc, opcode, data))
+    {
+        return false;
+    }
+
+    int64_t value;
+    if (opcode == OP_0 || (opcode >= OP_1 && opcode <= OP_16))
+    {
+        value = CScript::DecodeOP_N(opcode);
+    }
+    else if (opcode <= OP_PUSHDATA4)
+    {
+        try
+        {
+            value = CScriptNum(data, /*fRequireMinimal=*/true, /*nMaxNumSize=*/5).GetInt64();
+        }
+        catch (const scriptnum_error &)
+        {
+            return false;
+        }
+    }
+    else
+    {
+        return false;
+    }
+    if (value < 0 || value > std::numeric_limits<uint32_t>::max())
+    {
+        return false;
+    }
+    // Only the encoding ContextualCheckBlock compares against counts, e.g. not a push of a
+    // small height that has its own OP_N
+    const CScript expect = coinbase_height_prefix(value);
+    if (!std::equal(expect.begin(), expect.end(), script_sig.begin(), pc))
+    {
+        return false;
+    }
+    *height = value;
+    return true;
+}
+
+extern "C" uint32_t check_coinbase_height(
+    const unsigned char *tx_bytes,
+    const uint32_t tx_len,
+    const uint32_t height,
+    const Network network)
+{
+    CMutableTransaction mtx;
+    if (!deserialize_transaction(tx_bytes, tx_len, mtx))
+    {
+        return BLOCK_DESERIALIZE_FAILED;
+    }
+    const CTransaction tx(std::move(mtx));
+    if (!tx.IsCoinBase())
+    {
+        return BLOCK_BAD_CB_MISSING;
+    }
+    const Consensus::Params &params = get_consensus_params(network);
+    if (int64_t{height} < params.DeploymentHeight(Consensus::DEPLOYMENT_HEIGHTINCB))
+    {
+        return BLOCK_VALID;
+    }
+    // Enforce rule that the coinbase starts with serialized block height
+    const CScript expect = coinbase_height_prefix(height);
+    const CScript &script_sig = tx.vin[0].scriptSig;
+    if (script_sig.size() < expect.size() ||
+        !std::equal(expect.begin(), expect.end(), script_sig.begin()))
+    {
+        return BLOCK_BAD_CB_HEIGHT;
+    }
+    return BLOCK_VALID;
+}
+
+extern "C" int64_t get_block_subsidy(const uint32_t height, const Network network)
+{
+    return GetBlockSubsidy(height, get_consensus_params(network));
+}
+
+extern "C" uint32_t check_coinbase_value(
+    const unsigned char *block_bytes,
+    const uint32_t block_len,
+    const int64_t *input_values,
+    const uint32_t input_values_len,
+    const uint32_t height,
+    const Network network)
+{
+    CBlock block;
+    if (!deserialize_block(block_bytes, block_len, block))
+    {
+        return BLOCK_DESERIALIZE_FAILED;
+    }
+    if (block.vtx.empty() || !block.vtx[0]->IsCoinBase())
+    {
+        return BLOCK_BAD_CB_MISSING;
+    }
+    size_t input_count = 0;
+    for (size_t i = 1; i < block.vtx.size(); i++)
+    {
+        input_count += block.vtx[i]->vin.size();
+    }
+    if (input_count != input_values_len)
+    {
+        return BLOCK_INPUT_VALUES_MISMATCH;
+    }
+
+    BlockValidationState state;
+    if (!CheckCoinbaseValue(block, Span<const CAmount>(input_values, input_values_len), height, get_consensus_params(network), state))
+    {
+        return block_reject_code(state.GetRejectReason());
+    }
+    return BLOCK_VALID;
+}
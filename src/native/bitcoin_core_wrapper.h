@@ -8,6 +8,19 @@ extern "C"
 {
 #endif
 
+    /**
+     * Bitcoin networks whose consensus parameters can be selected over the FFI.
+     * Must be kept in sync with `Network` in src/network.rs
+     */
+    typedef enum
+    {
+        NETWORK_MAINNET = 0,
+        NETWORK_TESTNET3 = 1,
+        NETWORK_TESTNET4 = 2,
+        NETWORK_SIGNET = 3,
+        NETWORK_REGTEST = 4,
+    } Network;
+
     /**
      * Performs SHA256 hash on input data
      * @param[in]  input      Pointer to the input data to be hashed
@@ -16,6 +29,14 @@ extern "C"
      */
     void sha256_hash(const unsigned char *input, const uint32_t input_len, unsigned char *hash_result);
 
+    /**
+     * Computes the double SHA256 hash of each 64-byte block, as done for merkle tree nodes
+     * @param[in]  input      Pointer to `blocks` consecutive 64-byte inputs
+     * @param[in]  blocks     Number of inputs
+     * @param[out] output     Pointer to a pre-allocated buffer of `blocks` 32-byte hashes
+     */
+    void sha256d_64(const unsigned char *input, const uint32_t blocks, unsigned char *output);
+
     /**
      * Gets the double SHA256 hash of a Bitcoin block header
      * @param[in]  header_bytes   Pointer to the 80-byte Bitcoin block header
@@ -28,17 +49,60 @@ extern "C"
     /**
      * Validates a Bitcoin block header's proof of work using its embedded nBits
      * @param[in] header_bytes   Pointer to the 80-byte Bitcoin block header
+     * @param[in] network        Network whose powLimit the target is checked against
      * @return                  Returns true if the header's proof of work is valid,
      *                         false otherwise
      */
-    bool check_proof_of_work(const unsigned char *header_bytes);
+    bool check_proof_of_work(const unsigned char *header_bytes, const Network network);
+
+    /**
+     * Validates a header's proof of work using its embedded nBits like CheckProofOfWork,
+     * with a caller supplied powLimit instead of a network's
+     * @param[in] header_bytes   Pointer to the 80-byte Bitcoin block header
+     * @param[in] pow_limit      Pointer to the 32-byte little-endian largest allowed target
+     * @return                  Returns true if the header's proof of work is valid,
+     *                         false otherwise
+     */
+    bool check_proof_of_work_with_limit(const unsigned char *header_bytes, const unsigned char *pow_limit);
+
+    /**
+     * Checks a header's hash against a target other than its nBits, such as a mining pool's share target
+     * @param[in]  header_bytes          Pointer to the 80-byte Bitcoin block header
+     * @param[in]  target                Pointer to the 32-byte little-endian target, never met if zero
+     * @param[in]  network               Network whose powLimit the header's nBits are checked against
+     * @param[out] meets_network_target  Pointer to the flag set if the header also passes check_proof_of_work
+     * @return                          Returns true if the hash is at most the target,
+     *                                  false otherwise
+     */
+    bool check_hash_against_target(
+        const unsigned char *header_bytes,
+        const unsigned char *target,
+        const Network network,
+        bool *meets_network_target);
 
     /**
      * Gets the height of the last difficulty retarget for a given height
      * @param[in] height       The block height to check
+     * @param[in] network      Network whose difficulty adjustment interval is used
      * @return                The height of the last difficulty retarget
      */
-    uint32_t get_retarget_height(const uint32_t height);
+    uint32_t get_retarget_height(const uint32_t height, const Network network);
+
+    /**
+     * Checks whether new_nbits could follow old_nbits at a height, without the timestamps
+     * needed to compute the exact value, like PermittedDifficultyTransition
+     * @param[in] height       Height of the block with new_nbits
+     * @param[in] old_nbits    nBits of the block's parent
+     * @param[in] new_nbits    nBits of the block
+     * @param[in] network      Network whose consensus parameters are applied
+     * @return                Returns true if the transition is permitted, always on networks
+     *                        allowing min-difficulty blocks
+     */
+    bool permitted_difficulty_transition(
+        const uint32_t height,
+        const uint32_t old_nbits,
+        const uint32_t new_nbits,
+        const Network network);
 
     /**
      * Gets the proof of a Bitcoin block header
@@ -50,13 +114,88 @@ extern "C"
     bool get_block_proof(const unsigned char *header_bytes, unsigned char *proof);
 
     /**
-     * Validates the next required proof of work (nBits) for a block
+     * Expands compact nBits into a target with arith_uint256::SetCompact
+     * @param[in]  nbits      The compact target
+     * @param[out] target     Pointer to a pre-allocated 32-byte buffer for the little-endian target
+     * @param[out] negative   Pointer to the flag set if the sign bit is set on a non-zero mantissa
+     * @param[out] overflow   Pointer to the flag set if the target doesn't fit in 256 bits
+     */
+    void target_from_compact(const uint32_t nbits, unsigned char *target, bool *negative, bool *overflow);
+
+    /**
+     * Encodes a target as compact nBits with arith_uint256::GetCompact
+     * @param[in] target   Pointer to the 32-byte little-endian target
+     * @return            The compact target, rounded down to a 23-bit mantissa
+     */
+    uint32_t target_to_compact(const unsigned char *target);
+
+    /**
+     * Gets the difficulty of compact nBits relative to the minimum difficulty 0x1d00ffff,
+     * as reported by the getdifficulty RPC
+     * @param[in] nbits   The compact target
+     * @return           The difficulty as a floating-point number
+     */
+    double difficulty_from_compact(const uint32_t nbits);
+
+    /**
+     * Gets the expected number of hashes to meet a target, the formula of GetBlockProof
+     * @param[in]  target   Pointer to the 32-byte little-endian target
+     * @param[out] work     Pointer to a pre-allocated 32-byte buffer for the little-endian work,
+     *                      zero for a zero target
+     */
+    void target_to_work(const unsigned char *target, unsigned char *work);
+
+    /**
+     * Reasons a header can be rejected, named after the reject reasons used by
+     * Bitcoin Core's validation.cpp. Must be kept in sync with `ffi::HEADER_*` in src/lib.rs
+     * and `BitcoinError::from_validation_error` in src/error.rs
+     */
+    typedef enum
+    {
+        HEADER_VALID = 0,
+        HEADER_HIGH_HASH = 1,
+        HEADER_PREV_BLK_NOT_FOUND = 2,
+        HEADER_BAD_DIFFBITS = 3,
+        HEADER_TIME_TOO_OLD = 4,
+        HEADER_TIME_TIMEWARP_ATTACK = 5,
+        HEADER_BAD_VERSION = 6,
+        // 7 was the rejection of testnet4 retargets, which are computed since BIP94 support
+        HEADER_TIME_TOO_NEW = 8,
+        HEADER_DESERIALIZE_FAILED = 9,
+        HEADER_BAD_HEIGHT = 10,
+        // The testnet min-difficulty rule needs last_non_min_difficulty_bits, which wasn't passed
+        HEADER_LAST_BITS_REQUIRED = 11,
+    } HeaderValidationResult;
+
+    /**
+     * Details of a rejected header, filled in by the functions validating headers.
+     * Must be kept in sync with `ffi::ValidationError` in src/lib.rs
+     */
+    typedef struct
+    {
+        // HeaderValidationResult
+        uint32_t code;
+        // With HEADER_BAD_DIFFBITS, the nBits required by the chain and those of the header
+        uint32_t expected_bits;
+        uint32_t actual_bits;
+    } ValidationError;
+
+    /**
+     * Validates the next required proof of work (nBits) for a block. On networks enforcing BIP94, the
+     * first block of a period is also rejected if it is more than MAX_TIMEWARP seconds older than its parent
      *
      * @param[in]  last_retarget_header_bytes  Pointer to the 80-byte header at last retarget
      * @param[in]  previous_height             Height of the previous block
      * @param[in]  previous_header_bytes       Pointer to the 80-byte previous block header
      * @param[in]  header_bytes                Pointer to the 80-byte new block header
+     * @param[in]  last_non_min_difficulty_bits  Pointer to the nBits of the newest block up to the previous one that
+     *                                         is at a retarget height or not at the proof of work limit, see
+     *                                         get_last_non_min_difficulty_bits. May be null, in which case headers
+     *                                         the testnet min-difficulty rule needs it for are rejected with
+     *                                         HEADER_LAST_BITS_REQUIRED
+     * @param[in]  network                     Network whose consensus parameters are applied
      * @param[out] next_retarget_header_bytes  Pointer to a pre-allocated 80-byte buffer for the next retarget header
+     * @param[out] error                       Pointer to the details of the failure, set when false is returned
      * @return                                 Returns true if the header's nBits are valid,
      *                                         false otherwise
      */
@@ -65,7 +204,25 @@ extern "C"
         const uint32_t previous_height,
         const unsigned char *previous_header_bytes,
         const unsigned char *header_bytes,
-        unsigned char *next_retarget_header_bytes);
+        const uint32_t *last_non_min_difficulty_bits,
+        const Network network,
+        unsigned char *next_retarget_header_bytes,
+        ValidationError *error);
+
+    /**
+     * Updates the last_non_min_difficulty_bits passed to validate_next_work_required once a block is accepted.
+     * Stands in for the walk back through min-difficulty ancestors GetNextWorkRequired does on testnet
+     * @param[in] last_non_min_difficulty_bits  The value for the chain up to the block's parent
+     * @param[in] height                        Height of the block
+     * @param[in] header_bits                   nBits of the block
+     * @param[in] network                       Network whose consensus parameters are applied
+     * @return                                  Returns the value for the chain up to the block
+     */
+    uint32_t get_last_non_min_difficulty_bits(
+        const uint32_t last_non_min_difficulty_bits,
+        const uint32_t height,
+        const uint32_t header_bits,
+        const Network network);
 
     /**
      * Checks if a header properly connects to its previous header
@@ -78,6 +235,491 @@ extern "C"
         const unsigned char *header_bytes,
         const unsigned char *previous_header_bytes);
 
+    /**
+     * Number of timestamps needed to compute the median time past (CBlockIndex::nMedianTimeSpan)
+     */
+#define MEDIAN_TIME_SPAN 11
+
+    /**
+     * Snapshot of a validated header chain, enough to validate the header that extends it
+     * without a block index. Must be kept in sync with `ffi::HeaderChainState` in src/lib.rs
+     */
+    typedef struct
+    {
+        unsigned char tip_header[80];
+        uint32_t tip_height;
+        unsigned char last_retarget_header[80];
+        // little-endian arith_uint256
+        unsigned char chainwork[32];
+        // nBits of the newest block at a retarget height or not at the proof of work limit,
+        // see get_last_non_min_difficulty_bits
+        uint32_t last_non_min_difficulty_bits;
+        // timestamps of the tip and its ancestors, newest first;
+        // only the first min(tip_height + 1, MEDIAN_TIME_SPAN) entries are used
+        uint32_t timestamps[MEDIAN_TIME_SPAN];
+        Network network;
+    } HeaderChainState;
+
+    /**
+     * Validates a header against the chain it extends, applying the rules of
+     * CheckBlockHeader and ContextualCheckBlockHeader
+     * @param[in]  state         Pointer to the state of the chain being extended
+     * @param[in]  header_bytes  Pointer to the 80-byte new block header
+     * @param[in]  adjusted_time The caller's current unix time, for the time-too-new check
+     * @param[out] next_state    Pointer to a state that is overwritten with the extended chain on success
+     * @param[out] error         Pointer to the reason the header was rejected, set when false is returned
+     * @return                   Returns true if the header extends the chain,
+     *                           false otherwise
+     */
+    bool accept_block_header(
+        const HeaderChainState *state,
+        const unsigned char *header_bytes,
+        const int64_t adjusted_time,
+        HeaderChainState *next_state,
+        ValidationError *error);
+
+    /**
+     * Validates a chain of headers extending state with accept_block_header, in a single call.
+     * The consensus parameters are built once and headers are parsed without allocating
+     * @param[in]  state         Pointer to the state of the chain being extended
+     * @param[in]  headers       Pointer to count consecutive 80-byte block headers, oldest first
+     * @param[in]  count         Number of headers
+     * @param[in]  adjusted_time The caller's current unix time, for the time-too-new check
+     * @param[out] end_state     Pointer to a state that is overwritten with the extended chain on success
+     * @param[out] failed_index  Pointer to the index of the rejected header, set when false is returned
+     * @param[out] error         Pointer to the reason the header was rejected, set when false is returned
+     * @return                   Returns true if every header extends the chain,
+     *                           false otherwise
+     */
+    bool validate_header_chain(
+        const HeaderChainState *state,
+        const unsigned char *headers,
+        const uint32_t count,
+        const int64_t adjusted_time,
+        HeaderChainState *end_state,
+        uint32_t *failed_index,
+        ValidationError *error);
+
+    /**
+     * Gets the median of up to MEDIAN_TIME_SPAN block timestamps, like CBlockIndex::GetMedianTimePast
+     * @param[in] timestamps  Pointer to the timestamps of a block and its ancestors
+     * @param[in] count       Number of timestamps, at most MEDIAN_TIME_SPAN
+     * @return               The median timestamp
+     */
+    int64_t get_median_time_past(const uint32_t *timestamps, const uint32_t count);
+
+    /**
+     * Validates a header's timestamp against the median time past of its ancestors
+     * and the MAX_FUTURE_BLOCK_TIME limit
+     * @param[in]  header_bytes   Pointer to the 80-byte Bitcoin block header
+     * @param[in]  timestamps     Pointer to the timestamps of the previous block and its ancestors
+     * @param[in]  count          Number of timestamps, at most MEDIAN_TIME_SPAN
+     * @param[in]  adjusted_time  The caller's current unix time
+     * @param[out] error          Pointer to the reason the timestamp was rejected, set when false is returned
+     * @return                    Returns true if the timestamp is valid,
+     *                            false otherwise
+     */
+    bool check_header_time(
+        const unsigned char *header_bytes,
+        const uint32_t *timestamps,
+        const uint32_t count,
+        const int64_t adjusted_time,
+        ValidationError *error);
+
+    /**
+     * Computes the merkle root of a list of hashes, see ComputeMerkleRoot in consensus/merkle.h
+     * @param[in]  hashes   Pointer to count consecutive 32-byte hashes
+     * @param[in]  count    Number of hashes
+     * @param[out] root     Pointer to a pre-allocated 32-byte buffer where the root will be stored
+     * @param[out] mutated  Set to true if a duplicated subtree was found (CVE-2012-2459)
+     */
+    void compute_merkle_root(
+        const unsigned char *hashes,
+        const uint32_t count,
+        unsigned char *root,
+        bool *mutated);
+
+    /**
+     * Computes the merkle root of the transactions in a serialized block
+     * @param[in]  block_bytes  Pointer to the serialized block
+     * @param[in]  block_len    Length of the serialized block in bytes
+     * @param[out] root         Pointer to a pre-allocated 32-byte buffer where the root will be stored
+     * @param[out] mutated      Set to true if a duplicated subtree was found
+     * @return                  Returns true if the block was successfully deserialized,
+     *                          false otherwise
+     */
+    bool block_merkle_root(
+        const unsigned char *block_bytes,
+        const uint32_t block_len,
+        unsigned char *root,
+        bool *mutated);
+
+    /**
+     * Computes the merkle root of the witness transaction ids (wtxids) in a serialized block,
+     * with the coinbase wtxid taken as zero
+     * @param[in]  block_bytes  Pointer to the serialized block
+     * @param[in]  block_len    Length of the serialized block in bytes
+     * @param[out] root         Pointer to a pre-allocated 32-byte buffer where the root will be stored
+     * @param[out] mutated      Set to true if a duplicated subtree was found
+     * @return                  Returns true if the block was successfully deserialized and has a coinbase,
+     *                          false otherwise
+     */
+    bool block_witness_merkle_root(
+        const unsigned char *block_bytes,
+        const uint32_t block_len,
+        unsigned char *root,
+        bool *mutated);
+
+    /**
+     * Maximum merkle branch length, the depth of a tree with 2^32 leaves
+     */
+#define MAX_MERKLE_BRANCH_LENGTH 32
+
+    /**
+     * Computes the merkle branch proving the inclusion of the hash at position
+     * in the tree ComputeMerkleRoot builds over hashes
+     * @param[in]  hashes      Pointer to count consecutive 32-byte hashes
+     * @param[in]  count       Number of hashes
+     * @param[in]  position    Index of the hash to prove
+     * @param[out] branch      Pointer to a pre-allocated buffer of MAX_MERKLE_BRANCH_LENGTH 32-byte hashes
+     * @param[out] branch_len  Number of hashes written to branch
+     * @return                 Returns true if position is within hashes,
+     *                         false otherwise
+     */
+    bool compute_merkle_branch(
+        const unsigned char *hashes,
+        const uint32_t count,
+        const uint32_t position,
+        unsigned char *branch,
+        uint32_t *branch_len);
+
+    /**
+     * Verifies a merkle branch against a merkle root. The branch must be consistent with the shape
+     * of a tree branch_len levels deep: position must fit in it and only the last node of a level
+     * may be paired with itself. An internal node can still be passed off as a 64-byte
+     * transaction unless branch_len is checked against the depth of the tree
+     * @param[in] leaf        Pointer to the 32-byte hash being proven
+     * @param[in] branch      Pointer to branch_len consecutive 32-byte hashes
+     * @param[in] branch_len  Number of hashes in the branch
+     * @param[in] position    Index of the leaf in the tree
+     * @param[in] root        Pointer to the 32-byte merkle root
+     * @return                Returns true if the branch connects the leaf to the root,
+     *                        false otherwise
+     */
+    bool verify_merkle_branch(
+        const unsigned char *leaf,
+        const unsigned char *branch,
+        const uint32_t branch_len,
+        const uint32_t position,
+        const unsigned char *root);
+
+    /**
+     * Reasons a transaction can fail to deserialize, after the checks of Core's serialization code
+     * that throw. Must be kept in sync with `ffi::TX_*` in src/lib.rs
+     */
+    typedef enum
+    {
+        TX_OK = 0,
+        TX_END_OF_DATA = 1,
+        TX_NON_CANONICAL_COMPACT_SIZE = 2,
+        TX_SIZE_TOO_LARGE = 3,
+        TX_SUPERFLUOUS_WITNESS_RECORD = 4,
+        TX_UNKNOWN_OPTIONAL_DATA = 5,
+        TX_TRAILING_DATA = 6,
+        TX_OTHER = 7,
+    } TransactionDecodeResult;
+
+    /**
+     * Opaque owner of a deserialized CTransaction
+     */
+    typedef struct TransactionHandle TransactionHandle;
+
+    typedef struct
+    {
+        uint32_t version;
+        uint32_t lock_time;
+        uint32_t input_count;
+        uint32_t output_count;
+        unsigned char txid[32];
+        unsigned char wtxid[32];
+        uint32_t weight;
+    } TransactionInfo;
+
+    typedef struct
+    {
+        unsigned char prevout_hash[32];
+        uint32_t prevout_n;
+        uint32_t sequence;
+        const unsigned char *script_sig;
+        uint32_t script_sig_len;
+        uint32_t witness_len;
+    } TransactionInputInfo;
+
+    typedef struct
+    {
+        int64_t value;
+        const unsigned char *script_pubkey;
+        uint32_t script_pubkey_len;
+    } TransactionOutputInfo;
+
+    /**
+     * Deserializes a transaction in legacy or segwit (BIP144) encoding
+     * @param[in]  tx_bytes  Pointer to the serialized transaction
+     * @param[in]  tx_len    Length of the serialized transaction in bytes
+     * @param[out] handle    Set to a new handle on success, to be released with transaction_free
+     * @return               Returns TX_OK on success, otherwise the reason deserialization failed
+     */
+    uint32_t transaction_deserialize(
+        const unsigned char *tx_bytes,
+        const uint32_t tx_len,
+        TransactionHandle **handle);
+
+    /**
+     * Releases a handle returned by transaction_deserialize
+     */
+    void transaction_free(TransactionHandle *handle);
+
+    /**
+     * Gets the scalar fields, hashes and BIP141 weight of a transaction
+     */
+    void transaction_info(const TransactionHandle *handle, TransactionInfo *info);
+
+    /**
+     * Gets input index of a transaction. Script pointers stay valid until the handle is freed
+     */
+    void transaction_input(const TransactionHandle *handle, const uint32_t index, TransactionInputInfo *input);
+
+    /**
+     * Gets item of the witness stack of input index of a transaction
+     * @return  Pointer to the item, valid until the handle is freed
+     */
+    const unsigned char *transaction_input_witness(
+        const TransactionHandle *handle,
+        const uint32_t index,
+        const uint32_t item,
+        uint32_t *item_len);
+
+    /**
+     * Gets output index of a transaction. Script pointers stay valid until the handle is freed
+     */
+    void transaction_output(const TransactionHandle *handle, const uint32_t index, TransactionOutputInfo *output);
+
+    /**
+     * Reasons verify_script can fail, after the bitcoinconsensus_error values of the former
+     * libbitcoinconsensus. Must be kept in sync with `ffi::VERIFY_*` in src/lib.rs
+     */
+    typedef enum
+    {
+        VERIFY_OK = 0,
+        VERIFY_ERR_TX_INDEX = 1,
+        VERIFY_ERR_TX_DESERIALIZE = 2,
+        VERIFY_ERR_INVALID_FLAGS = 3,
+        VERIFY_ERR_SPENT_OUTPUTS_REQUIRED = 4,
+        VERIFY_ERR_SPENT_OUTPUTS_MISMATCH = 5,
+        VERIFY_ERR_SCRIPT = 6,
+    } ScriptVerifyResult;
+
+    /**
+     * Verifies a script input with VerifyScript, the same way bitcoinconsensus_verify_script_with_spent_outputs did
+     * @param[in]  script_sig          Pointer to the scriptSig of the spending input
+     * @param[in]  script_sig_len      Length of the scriptSig in bytes
+     * @param[in]  script_pubkey       Pointer to the scriptPubKey of the output being spent
+     * @param[in]  script_pubkey_len   Length of the scriptPubKey in bytes
+     * @param[in]  witness_items       Pointer to witness_len pointers to the witness stack items
+     * @param[in]  witness_item_lens   Pointer to witness_len lengths of the witness stack items
+     * @param[in]  witness_len         Number of witness stack items
+     * @param[in]  flags               SCRIPT_VERIFY_* flags supported by libbitcoinconsensus
+     * @param[in]  tx_bytes            Pointer to the serialized spending transaction
+     * @param[in]  tx_len              Length of the serialized spending transaction in bytes
+     * @param[in]  input_index         Index of the input being verified
+     * @param[in]  amount              Value of the output being spent, in satoshis
+     * @param[in]  spent_outputs       Pointer to the outputs spent by every input of the transaction, or null
+     * @param[in]  spent_outputs_len   Number of spent outputs
+     * @param[out] script_error        Set to the ScriptError reported by VerifyScript, mirrored by
+     *                                 `ffi::SCRIPT_ERR_*` in src/lib.rs
+     * @return                         Returns VERIFY_OK if the input is valid,
+     *                                 otherwise the reason verification failed
+     */
+    uint32_t verify_script(
+        const unsigned char *script_sig,
+        const uint32_t script_sig_len,
+        const unsigned char *script_pubkey,
+        const uint32_t script_pubkey_len,
+        const unsigned char *const *witness_items,
+        const uint32_t *witness_item_lens,
+        const uint32_t witness_len,
+        const uint32_t flags,
+        const unsigned char *tx_bytes,
+        const uint32_t tx_len,
+        const uint32_t input_index,
+        const int64_t amount,
+        const TransactionOutputInfo *spent_outputs,
+        const uint32_t spent_outputs_len,
+        uint32_t *script_error);
+
+    /**
+     * Verifies an ECDSA signature the way OP_CHECKSIG does, with lax DER parsing and S normalized
+     * @param[in] pubkey       Pointer to the serialized public key, compressed or uncompressed
+     * @param[in] pubkey_len   Length of the public key in bytes
+     * @param[in] sig          Pointer to the DER encoded signature, without sighash type
+     * @param[in] sig_len      Length of the signature in bytes
+     * @param[in] msg_hash     Pointer to the 32-byte message hash
+     * @return                 Returns true if the signature is valid,
+     *                         false otherwise
+     */
+    bool verify_ecdsa(
+        const unsigned char *pubkey,
+        const uint32_t pubkey_len,
+        const unsigned char *sig,
+        const uint32_t sig_len,
+        const unsigned char *msg_hash);
+
+    /**
+     * Verifies a BIP340 Schnorr signature
+     * @param[in] pubkey   Pointer to the 32-byte x-only public key
+     * @param[in] sig      Pointer to the 64-byte signature
+     * @param[in] msg      Pointer to the 32-byte message
+     * @return             Returns true if the signature is valid,
+     *                     false otherwise
+     */
+    bool verify_schnorr(const unsigned char *pubkey, const unsigned char *sig, const unsigned char *msg);
+
+    /**
+     * Reasons a block can be rejected, named after the reject reasons of Bitcoin Core's
+     * CheckBlock and CheckTransaction. Must be kept in sync with `ffi::BLOCK_*` in src/lib.rs
+     */
+    typedef enum
+    {
+        BLOCK_VALID = 0,
+        BLOCK_DESERIALIZE_FAILED = 1,
+        BLOCK_HIGH_HASH = 2,
+        BLOCK_BAD_TXNMRKLROOT = 3,
+        BLOCK_BAD_TXNS_DUPLICATE = 4,
+        BLOCK_BAD_BLK_LENGTH = 5,
+        BLOCK_BAD_CB_MISSING = 6,
+        BLOCK_BAD_CB_MULTIPLE = 7,
+        BLOCK_BAD_TXNS_VIN_EMPTY = 8,
+        BLOCK_BAD_TXNS_VOUT_EMPTY = 9,
+        BLOCK_BAD_TXNS_OVERSIZE = 10,
+        BLOCK_BAD_TXNS_VOUT_NEGATIVE = 11,
+        BLOCK_BAD_TXNS_VOUT_TOOLARGE = 12,
+        BLOCK_BAD_TXNS_TXOUTTOTAL_TOOLARGE = 13,
+        BLOCK_BAD_TXNS_INPUTS_DUPLICATE = 14,
+        BLOCK_BAD_CB_LENGTH = 15,
+        BLOCK_BAD_TXNS_PREVOUT_NULL = 16,
+        BLOCK_BAD_BLK_SIGOPS = 17,
+        BLOCK_OTHER = 18,
+        BLOCK_BAD_WITNESS_NONCE_SIZE = 19,
+        BLOCK_BAD_WITNESS_MERKLE_MATCH = 20,
+        BLOCK_UNEXPECTED_WITNESS = 21,
+        BLOCK_BAD_BLK_WEIGHT = 22,
+        BLOCK_BAD_CB_HEIGHT = 23,
+        BLOCK_BAD_CB_AMOUNT = 24,
+        BLOCK_BAD_TXNS_INPUTVALUES_OUTOFRANGE = 25,
+        BLOCK_BAD_TXNS_IN_BELOWOUT = 26,
+        BLOCK_BAD_TXNS_FEE_OUTOFRANGE = 27,
+        BLOCK_BAD_TXNS_ACCUMULATED_FEE_OUTOFRANGE = 28,
+        // Not a Core reject reason, the caller passed the wrong number of input values
+        BLOCK_INPUT_VALUES_MISMATCH = 29,
+    } BlockCheckResult;
+
+    /**
+     * Runs the context-free block checks of Bitcoin Core's CheckBlock: proof of work, merkle root
+     * and mutation, size and weight limits, coinbase position, CheckTransaction on every
+     * transaction and the legacy sigop limit. Signet block solutions are not checked
+     * @param[in] block_bytes  Pointer to the serialized block, with witness data
+     * @param[in] block_len    Length of the serialized block in bytes
+     * @param[in] network      Network whose powLimit the proof of work is checked against
+     * @return                 Returns BLOCK_VALID if the block passes, otherwise the reason it was rejected
+     */
+    uint32_t check_block(const unsigned char *block_bytes, const uint32_t block_len, const Network network);
+
+    /**
+     * Finds the witness commitment output of a coinbase transaction, see GetWitnessCommitmentIndex
+     * @param[in] tx_bytes  Pointer to the serialized coinbase transaction
+     * @param[in] tx_len    Length of the serialized transaction in bytes
+     * @return              Returns the index of the last output holding a witness commitment,
+     *                      -1 if there is none or the transaction fails to deserialize
+     */
+    int32_t get_witness_commitment_index(const unsigned char *tx_bytes, const uint32_t tx_len);
+
+    /**
+     * Computes the witness commitment of a block: the double SHA256 of its witness merkle root
+     * and the witness reserved value of its coinbase
+     * @param[in]  block_bytes  Pointer to the serialized block, with witness data
+     * @param[in]  block_len    Length of the serialized block in bytes
+     * @param[out] commitment   Pointer to a pre-allocated 32-byte buffer where the commitment will be stored
+     * @return                  Returns BLOCK_VALID on success, otherwise the reason no commitment
+     *                          can be computed
+     */
+    uint32_t compute_witness_commitment(
+        const unsigned char *block_bytes,
+        const uint32_t block_len,
+        unsigned char *commitment);
+
+    /**
+     * Runs the witness checks of Bitcoin Core's ContextualCheckBlock with segwit active: the witness
+     * commitment if the coinbase has one, no witness data otherwise, then the block weight limit
+     * @param[in] block_bytes  Pointer to the serialized block, with witness data
+     * @param[in] block_len    Length of the serialized block in bytes
+     * @return                 Returns BLOCK_VALID if the block passes, otherwise the reason it was rejected
+     */
+    uint32_t check_witness_commitment(const unsigned char *block_bytes, const uint32_t block_len);
+
+    /**
+     * Reads the block height BIP34 places at the start of a coinbase scriptSig
+     * @param[in]  tx_bytes  Pointer to the serialized coinbase transaction
+     * @param[in]  tx_len    Length of the serialized transaction in bytes
+     * @param[out] height    Set to the height if one was found
+     * @return               Returns true if the transaction is a coinbase whose scriptSig starts with
+     *                       a height encoded the way `CScript() << nHeight` does, false otherwise
+     */
+    bool coinbase_height(const unsigned char *tx_bytes, const uint32_t tx_len, uint32_t *height);
+
+    /**
+     * Runs the BIP34 check of Bitcoin Core's ContextualCheckBlock: from the network's BIP34Height on,
+     * the coinbase scriptSig must start with the serialized block height
+     * @param[in] tx_bytes  Pointer to the serialized coinbase transaction
+     * @param[in] tx_len    Length of the serialized transaction in bytes
+     * @param[in] height    Height of the block the coinbase belongs to
+     * @param[in] network   Network whose BIP34Height is used
+     * @return              Returns BLOCK_VALID if the coinbase passes, otherwise the reason it was rejected
+     */
+    uint32_t check_coinbase_height(
+        const unsigned char *tx_bytes,
+        const uint32_t tx_len,
+        const uint32_t height,
+        const Network network);
+
+    /**
+     * Gets the block subsidy at a height, see GetBlockSubsidy
+     * @param[in] height   The block height
+     * @param[in] network  Network whose halving interval is used
+     * @return             The subsidy in satoshis
+     */
+    int64_t get_block_subsidy(const uint32_t height, const Network network);
+
+    /**
+     * Runs the fee and coinbase value checks of Bitcoin Core's ConnectBlock, with the values of the
+     * spent outputs given by the caller instead of looked up in the UTXO set
+     * @param[in] block_bytes        Pointer to the serialized block, with witness data
+     * @param[in] block_len          Length of the serialized block in bytes
+     * @param[in] input_values       Pointer to the values of the outputs spent by every input of every
+     *                               transaction after the coinbase, in block order
+     * @param[in] input_values_len   Number of input values
+     * @param[in] height             Height of the block
+     * @param[in] network            Network whose halving interval is used
+     * @return                       Returns BLOCK_VALID if the coinbase claims at most the subsidy plus
+     *                               the fees, otherwise the reason the block was rejected
+     */
+    uint32_t check_coinbase_value(
+        const unsigned char *block_bytes,
+        const uint32_t block_len,
+        const int64_t *input_values,
+        const uint32_t input_values_len,
+        const uint32_t height,
+        const Network network);
+
 #ifdef __cplusplus
 }
 #endif
@@ -0,0 +1,31 @@
+// SHA-256 transform on top of the SP1 zkVM precompiles. Only built for the zkVM with the
+// sp1-sha256 feature, which makes crypto/sha256.cpp use it through ENABLE_SP1_SHA256 as patched
+// in by src/native/patches/sp1-sha256.patch
+#include "vendor/bitcoin/src/crypto/common.h"
+
+#include <cstddef>
+#include <cstdint>
+
+// Defined by the sp1-zkvm crate every guest program links against
+extern "C" void syscall_sha256_extend(uint32_t *w);
+extern "C" void syscall_sha256_compress(uint32_t *w, uint32_t *state);
+
+namespace sha256_sp1
+{
+void Transform(uint32_t *s, const unsigned char *chunk, size_t blocks)
+{
+    while (blocks--)
+    {
+        // The message schedule: the block as big-endian words, extended to 64 words
+        uint32_t w[64];
+        for (int i = 0; i < 16; i++)
+        {
+            w[i] = ReadBE32(chunk + 4 * i);
+        }
+        syscall_sha256_extend(w);
+        // Compresses the block into s, including the final addition of the previous state
+        syscall_sha256_compress(w, s);
+        chunk += 64;
+    }
+}
+} // namespace sha256_sp1
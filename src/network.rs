@@ -0,0 +1,14 @@
+/// Bitcoin network whose consensus parameters are used for validation.
+///
+/// The discriminants are shared with the `Network` enum in
+/// `src/native/bitcoin_core_wrapper.h` and must be kept in sync.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Network {
+    #[default]
+    Mainnet = 0,
+    Testnet3 = 1,
+    Testnet4 = 2,
+    Signet = 3,
+    Regtest = 4,
+}
@@ -0,0 +1,375 @@
+use alloc::vec::Vec;
+
+use crate::error::{ScriptError, ScriptVerifyError};
+use crate::ffi;
+use crate::transaction::{Transaction, TxOut};
+
+/// Script verification flags accepted by [`verify_script`], the subset of
+/// `SCRIPT_VERIFY_*` the former libbitcoinconsensus supported.
+pub const SCRIPT_VERIFY_NONE: u32 = 0;
+pub const SCRIPT_VERIFY_P2SH: u32 = 1 << 0;
+pub const SCRIPT_VERIFY_DERSIG: u32 = 1 << 2;
+pub const SCRIPT_VERIFY_NULLDUMMY: u32 = 1 << 4;
+pub const SCRIPT_VERIFY_CHECKLOCKTIMEVERIFY: u32 = 1 << 9;
+pub const SCRIPT_VERIFY_CHECKSEQUENCEVERIFY: u32 = 1 << 10;
+pub const SCRIPT_VERIFY_WITNESS: u32 = 1 << 11;
+pub const SCRIPT_VERIFY_TAPROOT: u32 = 1 << 17;
+pub const SCRIPT_VERIFY_ALL: u32 = SCRIPT_VERIFY_P2SH
+    | SCRIPT_VERIFY_DERSIG
+    | SCRIPT_VERIFY_NULLDUMMY
+    | SCRIPT_VERIFY_CHECKLOCKTIMEVERIFY
+    | SCRIPT_VERIFY_CHECKSEQUENCEVERIFY
+    | SCRIPT_VERIFY_WITNESS
+    | SCRIPT_VERIFY_TAPROOT;
+
+/// Verifies that `script_sig` and `witness` satisfy `script_pubkey` when spent by
+/// input `input_index` of `tx`, using Bitcoin Core's `VerifyScript`.
+///
+/// `amount` is the value of the output being spent. `spent_outputs` holds the
+/// outputs spent by every input of `tx` in order, and is required for taproot.
+/// This mirrors `bitcoinconsensus_verify_script_with_spent_outputs`, so `tx`
+/// must include its witnesses for segwit and taproot signatures to verify.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_script(
+    script_sig: &[u8],
+    script_pubkey: &[u8],
+    witness: &[Vec<u8>],
+    flags: u32,
+    tx: &Transaction,
+    input_index: u32,
+    amount: i64,
+    spent_outputs: Option<&[TxOut]>,
+) -> Result<(), ScriptVerifyError> {
+    let witness_items: Vec<*const u8> = witness.iter().map(|item| item.as_ptr()).collect();
+    let witness_item_lens: Vec<u32> = witness.iter().map(|item| item.len() as u32).collect();
+    let spent: Option<Vec<ffi::TransactionOutputInfo>> = spent_outputs.map(|outputs| {
+        outputs
+            .iter()
+            .map(|output| ffi::TransactionOutputInfo {
+                value: output.value,
+                script_pubkey: output.script_pubkey.as_ptr(),
+                script_pubkey_len: output.script_pubkey.len() as u32,
+            })
+            .collect()
+    });
+    let (spent_ptr, spent_len) = match &spent {
+        Some(outputs) => (outputs.as_ptr(), outputs.len() as u32),
+        None => (core::ptr::null(), 0),
+    };
+
+    let mut script_error = 0u32;
+    let result = unsafe {
+        ffi::verify_script(
+            script_sig.as_ptr(),
+            script_sig.len() as u32,
+            script_pubkey.as_ptr(),
+            script_pubkey.len() as u32,
+            witness_items.as_ptr(),
+            witness_item_lens.as_ptr(),
+            witness.len() as u32,
+            flags,
+            tx.as_bytes().as_ptr(),
+            tx.as_bytes().len() as u32,
+            input_index,
+            amount,
+            spent_ptr,
+            spent_len,
+            &mut script_error,
+        )
+    };
+    match result {
+        ffi::VERIFY_OK => Ok(()),
+        ffi::VERIFY_ERR_TX_INDEX => Err(ScriptVerifyError::TxIndex),
+        ffi::VERIFY_ERR_TX_DESERIALIZE => Err(ScriptVerifyError::TxDeserialize),
+        ffi::VERIFY_ERR_INVALID_FLAGS => Err(ScriptVerifyError::InvalidFlags),
+        ffi::VERIFY_ERR_SPENT_OUTPUTS_REQUIRED => Err(ScriptVerifyError::SpentOutputsRequired),
+        ffi::VERIFY_ERR_SPENT_OUTPUTS_MISMATCH => Err(ScriptVerifyError::SpentOutputsMismatch),
+        ffi::VERIFY_ERR_SCRIPT => Err(ScriptVerifyError::Script(ScriptError::from_code(
+            script_error,
+        ))),
+        code => Err(ScriptVerifyError::Unknown(code)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::hex;
+
+    // Two-input segwit transaction, see transaction::tests::SEGWIT_TX
+    const SEGWIT_TX: [u8; 145] = hex!("02000000000102000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f0100000000fdffffff202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f0000000003515253ffffffff0250c3000000000000160014ababababababababababababababababababababd204000000000000016a0203010203000000350c00");
+
+    // OP_2 OP_EQUAL, spent through P2WSH by the witness [OP_2 OP_EQUAL]
+    const WITNESS_SCRIPT: [u8; 2] = hex!("5287");
+    // OP_0 <sha256(WITNESS_SCRIPT)>
+    const P2WSH: [u8; 34] =
+        hex!("002096cc3caea92277647f1a513b9ca649fe93845566c0a29e69583a1e4bf67dbd71");
+
+    // "P2PK" case of Bitcoin Core's script_tests.json
+    const P2PK_SPEND_TX: [u8; 132] = hex!("01000000019ce5586f04dd407719ab7e2ed3583583b9022f29652702cfac5ed082013461fe000000004847304402200a5c6163f07b8d3b013c4d1d6dba25e780b39658d79ba37af7057a3b7f15ffa102201fd9b4eaa9943f734928b99a83592c2e7bf342ea2680f6a2bb705167966b742001ffffffff0100000000000000000000000000");
+    const P2PK_SCRIPT_PUBKEY: [u8; 67] = hex!("410479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8ac");
+
+    // Transaction of BIP341's wallet test vectors with only its first input signed, through the
+    // key path, and the outputs it spends
+    const TAPROOT_TX: [u8; 531] = hex!("020000000001097de20cbff686da83a54981d2b9bab3586f4ca7e48f57f5b55963115f3b334e9c010000000000000000d7b7cab57b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd990000000000fffffffff8e1f583384333689228c5d28eac13366be082dc57441760d957275419a418420000000000fffffffff0689180aa63b30cb162a73c6d2a38b7eeda2a83ece74310fda0843ad604853b0100000000feffffffaa5202bdf6d8ccd2ee0f0202afbbb7461d9264a25e5bfd3c5a52ee1239e0ba6c0000000000feffffff956149bdc66faa968eb2be2d2faa29718acbfe3941215893a2a3446d32acd050000000000000000000e664b9773b88c09c32cb70a2a3e4da0ced63b7ba3b22f848531bbb1d5d5f4c94010000000000000000e9aa6b8e6c9de67619e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf0000000000ffffffffa778eb6a263dc090464cd125c466b5a99667720b1c110468831d058aa1b82af10100000000ffffffff0200ca9a3b000000001976a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac807840cb0000000020ac9a87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b0141ed7c1647cb97379e76892be0cacff57ec4a7102aa24296ca39af7541246d8ff14d38958d4cc1e2e478e4d4a764bbfd835b16d4e314b72937b29833060b87276c0300000000000000000065cd1d");
+    const TAPROOT_SPENT: [(&[u8], i64); 9] = [
+        (
+            hex!("512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343").as_slice(),
+            420000000,
+        ),
+        (
+            hex!("5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3").as_slice(),
+            462000000,
+        ),
+        (
+            hex!("76a914751e76e8199196d454941c45d1b3a323f1433bd688ac").as_slice(),
+            294000000,
+        ),
+        (
+            hex!("5120e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e").as_slice(),
+            504000000,
+        ),
+        (
+            hex!("512091b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605").as_slice(),
+            630000000,
+        ),
+        (
+            hex!("00147dd65592d0ab2fe0d0257d571abf032cd9db93dc").as_slice(),
+            378000000,
+        ),
+        (
+            hex!("512075169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831").as_slice(),
+            672000000,
+        ),
+        (
+            hex!("5120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5").as_slice(),
+            546000000,
+        ),
+        (
+            hex!("512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220").as_slice(),
+            588000000,
+        ),
+    ];
+    const TAPROOT_SIG: [u8; 65] = hex!("ed7c1647cb97379e76892be0cacff57ec4a7102aa24296ca39af7541246d8ff14d38958d4cc1e2e478e4d4a764bbfd835b16d4e314b72937b29833060b87276c03");
+
+    fn tx() -> Transaction {
+        Transaction::from_bytes(&SEGWIT_TX).unwrap()
+    }
+
+    #[test]
+    fn test_verify_script_bare() {
+        let tx = tx();
+        // scriptSig OP_1 against scriptPubKey OP_1 OP_EQUAL
+        assert!(verify_script(
+            &[0x51],
+            &[0x51, 0x87],
+            &[],
+            SCRIPT_VERIFY_NONE,
+            &tx,
+            0,
+            0,
+            None
+        )
+        .is_ok());
+        assert_eq!(
+            verify_script(
+                &[0x52],
+                &[0x51, 0x87],
+                &[],
+                SCRIPT_VERIFY_NONE,
+                &tx,
+                0,
+                0,
+                None
+            ),
+            Err(ScriptVerifyError::Script(ScriptError::EvalFalse))
+        );
+        assert_eq!(
+            verify_script(&[0x51], &[0x6a], &[], SCRIPT_VERIFY_NONE, &tx, 0, 0, None),
+            Err(ScriptVerifyError::Script(ScriptError::OpReturn))
+        );
+    }
+
+    #[test]
+    fn test_verify_script_p2wsh() {
+        let tx = tx();
+        let flags = SCRIPT_VERIFY_P2SH | SCRIPT_VERIFY_WITNESS;
+        let witness = vec![vec![0x02], WITNESS_SCRIPT.to_vec()];
+        assert!(verify_script(&[], &P2WSH, &witness, flags, &tx, 0, 50000, None).is_ok());
+
+        let wrong_item = vec![vec![0x03], WITNESS_SCRIPT.to_vec()];
+        assert_eq!(
+            verify_script(&[], &P2WSH, &wrong_item, flags, &tx, 0, 50000, None),
+            Err(ScriptVerifyError::Script(ScriptError::EvalFalse))
+        );
+        assert_eq!(
+            verify_script(
+                &[],
+                &P2WSH,
+                &[vec![0x02], vec![0x53, 0x87]],
+                flags,
+                &tx,
+                0,
+                50000,
+                None
+            ),
+            Err(ScriptVerifyError::Script(
+                ScriptError::WitnessProgramMismatch
+            ))
+        );
+        assert_eq!(
+            verify_script(&[0x00], &P2WSH, &witness, flags, &tx, 0, 50000, None),
+            Err(ScriptVerifyError::Script(ScriptError::WitnessMalleated))
+        );
+        // Without WITNESS the program is an anyone-can-spend push
+        assert!(verify_script(&[], &P2WSH, &[], SCRIPT_VERIFY_P2SH, &tx, 0, 50000, None).is_ok());
+    }
+
+    #[test]
+    fn test_verify_script_errors() {
+        let tx = tx();
+        let spent = [
+            TxOut {
+                value: 50000,
+                script_pubkey: P2WSH.to_vec(),
+            },
+            TxOut {
+                value: 1234,
+                script_pubkey: vec![0x51],
+            },
+        ];
+        assert_eq!(
+            verify_script(&[0x51], &[0x51], &[], 1 << 1, &tx, 0, 0, None),
+            Err(ScriptVerifyError::InvalidFlags)
+        );
+        assert_eq!(
+            verify_script(
+                &[0x51],
+                &[0x51],
+                &[],
+                SCRIPT_VERIFY_WITNESS,
+                &tx,
+                0,
+                0,
+                None
+            ),
+            Err(ScriptVerifyError::InvalidFlags)
+        );
+        assert_eq!(
+            verify_script(&[0x51], &[0x51], &[], SCRIPT_VERIFY_NONE, &tx, 2, 0, None),
+            Err(ScriptVerifyError::TxIndex)
+        );
+        assert_eq!(
+            verify_script(&[0x51], &[0x51], &[], SCRIPT_VERIFY_ALL, &tx, 0, 0, None),
+            Err(ScriptVerifyError::SpentOutputsRequired)
+        );
+        assert_eq!(
+            verify_script(
+                &[0x51],
+                &[0x51],
+                &[],
+                SCRIPT_VERIFY_ALL,
+                &tx,
+                0,
+                0,
+                Some(&spent[..1])
+            ),
+            Err(ScriptVerifyError::SpentOutputsMismatch)
+        );
+        let witness = vec![vec![0x02], WITNESS_SCRIPT.to_vec()];
+        assert!(verify_script(
+            &[],
+            &P2WSH,
+            &witness,
+            SCRIPT_VERIFY_ALL,
+            &tx,
+            0,
+            50000,
+            Some(&spent)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_verify_script_ecdsa() {
+        let tx = Transaction::from_bytes(&P2PK_SPEND_TX).unwrap();
+        let script_sig = tx.inputs()[0].script_sig.clone();
+        let flags = SCRIPT_VERIFY_P2SH | SCRIPT_VERIFY_DERSIG;
+        assert!(verify_script(
+            &script_sig,
+            &P2PK_SCRIPT_PUBKEY,
+            &[],
+            flags,
+            &tx,
+            0,
+            0,
+            None
+        )
+        .is_ok());
+
+        let mut bad_sig = script_sig.clone();
+        bad_sig[20] ^= 1;
+        assert_eq!(
+            verify_script(&bad_sig, &P2PK_SCRIPT_PUBKEY, &[], flags, &tx, 0, 0, None),
+            Err(ScriptVerifyError::Script(ScriptError::EvalFalse))
+        );
+    }
+
+    #[test]
+    fn test_verify_script_taproot() {
+        let tx = Transaction::from_bytes(&TAPROOT_TX).unwrap();
+        let spent: Vec<TxOut> = TAPROOT_SPENT
+            .iter()
+            .map(|(script_pubkey, value)| TxOut {
+                value: *value,
+                script_pubkey: script_pubkey.to_vec(),
+            })
+            .collect();
+        let (script_pubkey, amount) = TAPROOT_SPENT[0];
+        let witness = vec![TAPROOT_SIG.to_vec()];
+        assert!(verify_script(
+            &[],
+            script_pubkey,
+            &witness,
+            SCRIPT_VERIFY_ALL,
+            &tx,
+            0,
+            amount,
+            Some(&spent)
+        )
+        .is_ok());
+
+        let mut bad_sig = TAPROOT_SIG;
+        bad_sig[0] ^= 1;
+        assert_eq!(
+            verify_script(
+                &[],
+                script_pubkey,
+                &[bad_sig.to_vec()],
+                SCRIPT_VERIFY_ALL,
+                &tx,
+                0,
+                amount,
+                Some(&spent)
+            ),
+            Err(ScriptVerifyError::Script(ScriptError::SchnorrSig))
+        );
+
+        // The signature commits to every spent amount
+        let mut wrong_amounts = spent.clone();
+        wrong_amounts[1].value += 1;
+        assert_eq!(
+            verify_script(
+                &[],
+                script_pubkey,
+                &witness,
+                SCRIPT_VERIFY_ALL,
+                &tx,
+                0,
+                amount,
+                Some(&wrong_amounts)
+            ),
+            Err(ScriptVerifyError::Script(ScriptError::SchnorrSig))
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use crate::ffi;
+
+/// Verifies a DER encoded ECDSA signature over `msg_hash` with the same rules as
+/// `OP_CHECKSIG`: lax DER parsing, and high S values are accepted.
+///
+/// `pubkey` is a compressed or uncompressed public key, and `signature` must not
+/// include the trailing sighash type byte.
+pub fn verify_ecdsa(pubkey: &[u8], signature: &[u8], msg_hash: &[u8; 32]) -> bool {
+    unsafe {
+        ffi::verify_ecdsa(
+            pubkey.as_ptr(),
+            pubkey.len() as u32,
+            signature.as_ptr(),
+            signature.len() as u32,
+            msg_hash.as_ptr(),
+        )
+    }
+}
+
+/// Verifies a BIP340 Schnorr signature over `msg` for the x-only public key `pubkey`.
+pub fn verify_schnorr(pubkey: &[u8; 32], signature: &[u8; 64], msg: &[u8; 32]) -> bool {
+    unsafe { ffi::verify_schnorr(pubkey.as_ptr(), signature.as_ptr(), msg.as_ptr()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::hex;
+
+    // "P2PK" case of Bitcoin Core's script_tests.json, with the legacy SIGHASH_ALL digest
+    // of its spending transaction
+    const ECDSA_PUBKEY: [u8; 65] = hex!("0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8");
+    const ECDSA_SIG: [u8; 70] = hex!("304402200a5c6163f07b8d3b013c4d1d6dba25e780b39658d79ba37af7057a3b7f15ffa102201fd9b4eaa9943f734928b99a83592c2e7bf342ea2680f6a2bb705167966b7420");
+    const ECDSA_SIGHASH: [u8; 32] =
+        hex!("cd3808fc5cbf9f2190d9b867b4498f234212984b8c879f296cf8e792b622a2f4");
+
+    // First key path spend of BIP341's wallet test vectors
+    const SCHNORR_PUBKEY: [u8; 32] =
+        hex!("53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343");
+    const SCHNORR_SIG: [u8; 64] = hex!("ed7c1647cb97379e76892be0cacff57ec4a7102aa24296ca39af7541246d8ff14d38958d4cc1e2e478e4d4a764bbfd835b16d4e314b72937b29833060b87276c");
+    const SCHNORR_MSG: [u8; 32] =
+        hex!("2514a6272f85cfa0f45eb907fcb0d121b808ed37c6ea160a5a9046ed5526d555");
+
+    #[test]
+    fn test_verify_ecdsa() {
+        assert!(verify_ecdsa(&ECDSA_PUBKEY, &ECDSA_SIG, &ECDSA_SIGHASH));
+
+        let mut msg = ECDSA_SIGHASH;
+        msg[0] ^= 1;
+        assert!(!verify_ecdsa(&ECDSA_PUBKEY, &ECDSA_SIG, &msg));
+
+        let mut sig = ECDSA_SIG;
+        sig[10] ^= 1;
+        assert!(!verify_ecdsa(&ECDSA_PUBKEY, &sig, &ECDSA_SIGHASH));
+
+        assert!(!verify_ecdsa(
+            &ECDSA_PUBKEY[..33],
+            &ECDSA_SIG,
+            &ECDSA_SIGHASH
+        ));
+        assert!(!verify_ecdsa(&[], &ECDSA_SIG, &ECDSA_SIGHASH));
+        assert!(!verify_ecdsa(&ECDSA_PUBKEY, &[], &ECDSA_SIGHASH));
+    }
+
+    #[test]
+    fn test_verify_schnorr() {
+        assert!(verify_schnorr(&SCHNORR_PUBKEY, &SCHNORR_SIG, &SCHNORR_MSG));
+
+        let mut msg = SCHNORR_MSG;
+        msg[31] ^= 1;
+        assert!(!verify_schnorr(&SCHNORR_PUBKEY, &SCHNORR_SIG, &msg));
+
+        let mut sig = SCHNORR_SIG;
+        sig[63] ^= 1;
+        assert!(!verify_schnorr(&SCHNORR_PUBKEY, &sig, &SCHNORR_MSG));
+
+        // Not the x coordinate of a point on the curve
+        assert!(!verify_schnorr(&[0xff; 32], &SCHNORR_SIG, &SCHNORR_MSG));
+    }
+}
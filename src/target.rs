@@ -0,0 +1,280 @@
+use core::fmt;
+
+use crate::chainwork::ChainWork;
+use crate::error::CompactError;
+use crate::ffi;
+use crate::network::Network;
+use crate::uint256::U256;
+
+/// A proof of work target, the largest block hash that meets it.
+///
+/// Ordered like the integers Bitcoin Core compares hashes with, and formatted as the
+/// `target` field of `getblockheader`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Target(U256);
+
+impl Target {
+    pub const ZERO: Target = Target(U256::ZERO);
+
+    /// Reads a target in the byte order of block hashes as returned by `get_block_hash`.
+    pub fn from_le_bytes(bytes: [u8; 32]) -> Self {
+        Self(U256::from_le_bytes(bytes))
+    }
+
+    pub fn to_le_bytes(&self) -> [u8; 32] {
+        self.0.to_le_bytes()
+    }
+
+    /// Reads a target in the byte order of its hex form, most significant byte first.
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        Self(U256::from_be_bytes(bytes))
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        self.0.to_be_bytes()
+    }
+
+    /// Encodes the target as compact nBits, see `arith_uint256::GetCompact`.
+    ///
+    /// Only the 23 most significant bits are kept, the rest is rounded down.
+    pub fn to_compact(&self) -> u32 {
+        unsafe { ffi::target_to_compact(self.to_le_bytes().as_ptr()) }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+impl fmt::LowerHex for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+/// Expands compact nBits into a target, see `arith_uint256::SetCompact`.
+///
+/// nBits can be negative and overflow at once, the error then has both flags set.
+pub fn target_from_compact(bits: u32) -> Result<Target, CompactError> {
+    let mut target = [0u8; 32];
+    let mut negative = false;
+    let mut overflow = false;
+    unsafe {
+        ffi::target_from_compact(bits, target.as_mut_ptr(), &mut negative, &mut overflow);
+    }
+    if negative || overflow {
+        Err(CompactError { negative, overflow })
+    } else {
+        Ok(Target::from_le_bytes(target))
+    }
+}
+
+/// Difficulty of compact nBits as reported by the `getdifficulty` RPC, the ratio of
+/// the target of `0x1d00ffff` to the target they encode.
+pub fn difficulty_from_compact(bits: u32) -> f64 {
+    unsafe { ffi::difficulty_from_compact(bits) }
+}
+
+/// Expected number of hashes to meet `target`, zero for a zero target.
+///
+/// For a target read from nBits this is the block proof of `GetBlockProof`.
+pub fn target_to_work(target: &Target) -> ChainWork {
+    let mut work = [0u8; 32];
+    unsafe { ffi::target_to_work(target.to_le_bytes().as_ptr(), work.as_mut_ptr()) };
+    ChainWork::from_le_bytes(work)
+}
+
+/// Like [`crate::check_proof_of_work`], with `pow_limit` as the largest target the header's
+/// nBits may encode instead of the network's.
+pub fn check_proof_of_work_with_limit(header: &[u8; 80], pow_limit: &Target) -> bool {
+    unsafe {
+        ffi::check_proof_of_work_with_limit(header.as_ptr(), pow_limit.to_le_bytes().as_ptr())
+    }
+}
+
+/// Outcome of [`check_hash_against_target`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShareCheck {
+    /// The header hash is at most the target it was checked against
+    pub meets_target: bool,
+    /// The header also passes [`crate::check_proof_of_work`], making it a block candidate
+    pub meets_network_target: bool,
+}
+
+/// Checks the header hash against `target` rather than the header's nBits, e.g. a share target
+/// assigned by a mining pool. A zero target is never met, as in `CheckProofOfWork`.
+///
+/// Also reports whether the header meets its own nBits under `network`'s proof of work limit, so
+/// shares that are valid blocks are found in the same call.
+pub fn check_hash_against_target(
+    header: &[u8; 80],
+    target: &Target,
+    network: Network,
+) -> ShareCheck {
+    let mut meets_network_target = false;
+    let meets_target = unsafe {
+        ffi::check_hash_against_target(
+            header.as_ptr(),
+            target.to_le_bytes().as_ptr(),
+            network,
+            &mut meets_network_target,
+        )
+    };
+    ShareCheck {
+        meets_target,
+        meets_network_target,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::BlockHeader;
+    use alloy_primitives::hex;
+
+    const HEADER_40320: [u8; 80] = hex!("010000001a231097b6ab6279c80f24674a2c8ee5b9a848e1d45715ad89b6358100000000a822bafe6ed8600e3ffce6d61d10df1927eafe9bbf677cb44c4d209f143c6ba8db8c784b5746651cce222118");
+
+    #[test]
+    fn test_target_from_compact() {
+        let target = target_from_compact(0x1d00ffff).unwrap();
+        assert_eq!(
+            target.to_string(),
+            "00000000ffff0000000000000000000000000000000000000000000000000000"
+        );
+        assert_eq!(target.to_compact(), 0x1d00ffff);
+        assert_eq!(Target::from_be_bytes(target.to_be_bytes()), target);
+
+        // mantissa bits shifted out below the size are dropped, so the encoding is normalized
+        let target = target_from_compact(0x01123456).unwrap();
+        assert_eq!(target.to_le_bytes()[..2], [0x12, 0]);
+        assert_eq!(target.to_compact(), 0x01120000);
+        assert_eq!(target_from_compact(0), Ok(Target::ZERO));
+        assert_eq!(target_from_compact(0x00923456), Ok(Target::ZERO));
+
+        let negative = CompactError {
+            negative: true,
+            overflow: false,
+        };
+        let overflow = CompactError {
+            negative: false,
+            overflow: true,
+        };
+        assert_eq!(target_from_compact(0x01fedcba), Err(negative));
+        assert_eq!(target_from_compact(0xff123456), Err(overflow));
+        assert_eq!(target_from_compact(0x2101ffff), Err(overflow));
+        let both = target_from_compact(0xff923456).unwrap_err();
+        assert_eq!(
+            both,
+            CompactError {
+                negative: true,
+                overflow: true
+            }
+        );
+        assert_eq!(
+            both.to_string(),
+            "negative compact target overflows 256 bits"
+        );
+        assert!(target_from_compact(0x2100ffff).is_ok());
+
+        assert!(
+            target_from_compact(0x1c654657).unwrap() < target_from_compact(0x1d00ffff).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_difficulty_from_compact() {
+        assert_eq!(difficulty_from_compact(0x1d00ffff), 1.0);
+        // block 40320
+        assert_eq!(difficulty_from_compact(0x1c654657), 2.527738215072359);
+        assert_eq!(difficulty_from_compact(0x207fffff), 4.6565423739069247e-10);
+    }
+
+    #[test]
+    fn test_target_to_work() {
+        let header = BlockHeader::from_bytes(&HEADER_40320);
+        let target = target_from_compact(header.bits).unwrap();
+        assert_eq!(target_to_work(&target), header.block_proof());
+        assert_eq!(target_to_work(&Target::ZERO), ChainWork::ZERO);
+
+        // the largest target is met by any hash
+        let mut one = [0u8; 32];
+        one[0] = 1;
+        assert_eq!(
+            target_to_work(&Target::from_le_bytes([0xff; 32])),
+            ChainWork::from_le_bytes(one)
+        );
+    }
+
+    #[test]
+    fn test_check_proof_of_work_with_limit() {
+        let mainnet_limit = target_from_compact(0x1d00ffff).unwrap();
+        assert!(check_proof_of_work_with_limit(
+            &HEADER_40320,
+            &mainnet_limit
+        ));
+        // the nBits of the header encode a target above this limit
+        let lower_limit = target_from_compact(0x1c654656).unwrap();
+        assert!(!check_proof_of_work_with_limit(&HEADER_40320, &lower_limit));
+
+        let regtest_genesis = hex!("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff7f2002000000");
+        let regtest_limit = Target::from_be_bytes({
+            let mut limit = [0xff; 32];
+            limit[0] = 0x7f;
+            limit
+        });
+        assert!(check_proof_of_work_with_limit(
+            &regtest_genesis,
+            &regtest_limit
+        ));
+        assert!(!check_proof_of_work_with_limit(
+            &regtest_genesis,
+            &mainnet_limit
+        ));
+    }
+
+    #[test]
+    fn test_check_hash_against_target() {
+        let hash = Target::from_le_bytes(crate::get_block_hash(&HEADER_40320).unwrap());
+        let share_target = target_from_compact(0x1d00ffff).unwrap();
+        assert_eq!(
+            check_hash_against_target(&HEADER_40320, &share_target, Network::Mainnet),
+            ShareCheck {
+                meets_target: true,
+                meets_network_target: true
+            }
+        );
+        // a hash equal to the target meets it
+        assert!(check_hash_against_target(&HEADER_40320, &hash, Network::Mainnet).meets_target);
+        assert_eq!(
+            check_hash_against_target(
+                &HEADER_40320,
+                &target_from_compact(0x03000001).unwrap(),
+                Network::Mainnet
+            ),
+            ShareCheck {
+                meets_target: false,
+                meets_network_target: true
+            }
+        );
+        assert!(
+            !check_hash_against_target(&HEADER_40320, &Target::ZERO, Network::Mainnet).meets_target
+        );
+
+        // a share at the regtest difficulty is no mainnet block
+        let regtest_genesis = hex!("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff7f2002000000");
+        let share_target = target_from_compact(0x207fffff).unwrap();
+        assert_eq!(
+            check_hash_against_target(&regtest_genesis, &share_target, Network::Mainnet),
+            ShareCheck {
+                meets_target: true,
+                meets_network_target: false
+            }
+        );
+        assert!(
+            check_hash_against_target(&regtest_genesis, &share_target, Network::Regtest)
+                .meets_network_target
+        );
+    }
+}
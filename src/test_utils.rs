@@ -0,0 +1,10 @@
+use crate::header::BlockHeader;
+use crate::network::Network;
+
+// Grinds the nonce of a regtest header until it meets its own target
+pub(crate) fn mine(mut header: BlockHeader) -> BlockHeader {
+    while !header.check_proof_of_work(Network::Regtest) {
+        header.nonce += 1;
+    }
+    header
+}
@@ -0,0 +1,345 @@
+use alloc::vec::Vec;
+
+use crate::error::TransactionDecodeError;
+use crate::ffi;
+
+/// Scale factor between non-witness and witness bytes in BIP141 weight.
+pub const WITNESS_SCALE_FACTOR: u32 = 4;
+
+/// Reference to an output of a previous transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutPoint {
+    /// Txid of the previous transaction, in internal byte order
+    pub txid: [u8; 32],
+    pub vout: u32,
+}
+
+impl OutPoint {
+    /// Returns true for the null prevout spent by coinbase inputs.
+    pub fn is_null(&self) -> bool {
+        self.txid == [0u8; 32] && self.vout == u32::MAX
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TxIn {
+    pub previous_output: OutPoint,
+    pub script_sig: Vec<u8>,
+    pub sequence: u32,
+    pub witness: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TxOut {
+    /// Value in satoshis
+    pub value: i64,
+    pub script_pubkey: Vec<u8>,
+}
+
+/// A transaction deserialized by Bitcoin Core's `CTransaction`.
+///
+/// Hashes are in internal byte order, the reverse of how block explorers display them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Transaction {
+    version: u32,
+    inputs: Vec<TxIn>,
+    outputs: Vec<TxOut>,
+    lock_time: u32,
+    txid: [u8; 32],
+    wtxid: [u8; 32],
+    weight: u32,
+    bytes: Vec<u8>,
+}
+
+impl Transaction {
+    /// Deserializes a transaction from its legacy or segwit (BIP144) wire encoding.
+    ///
+    /// Trailing bytes after the transaction are rejected.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TransactionDecodeError> {
+        let mut handle = core::ptr::null_mut();
+        let result = unsafe {
+            ffi::transaction_deserialize(bytes.as_ptr(), bytes.len() as u32, &mut handle)
+        };
+        match result {
+            ffi::TX_OK => {}
+            ffi::TX_END_OF_DATA => return Err(TransactionDecodeError::EndOfData),
+            ffi::TX_NON_CANONICAL_COMPACT_SIZE => {
+                return Err(TransactionDecodeError::NonCanonicalCompactSize)
+            }
+            ffi::TX_SIZE_TOO_LARGE => return Err(TransactionDecodeError::SizeTooLarge),
+            ffi::TX_SUPERFLUOUS_WITNESS_RECORD => {
+                return Err(TransactionDecodeError::SuperfluousWitnessRecord)
+            }
+            ffi::TX_UNKNOWN_OPTIONAL_DATA => {
+                return Err(TransactionDecodeError::UnknownOptionalData)
+            }
+            ffi::TX_TRAILING_DATA => return Err(TransactionDecodeError::TrailingData),
+            _ => return Err(TransactionDecodeError::Other),
+        }
+
+        let mut info = ffi::TransactionInfo::default();
+        let transaction = unsafe {
+            ffi::transaction_info(handle, &mut info);
+            let inputs = (0..info.input_count)
+                .map(|index| read_input(handle, index))
+                .collect();
+            let outputs = (0..info.output_count)
+                .map(|index| read_output(handle, index))
+                .collect();
+            ffi::transaction_free(handle);
+            Transaction {
+                version: info.version,
+                inputs,
+                outputs,
+                lock_time: info.lock_time,
+                txid: info.txid,
+                wtxid: info.wtxid,
+                weight: info.weight,
+                bytes: bytes.to_vec(),
+            }
+        };
+        Ok(transaction)
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn inputs(&self) -> &[TxIn] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[TxOut] {
+        &self.outputs
+    }
+
+    pub fn lock_time(&self) -> u32 {
+        self.lock_time
+    }
+
+    /// Hash of the transaction without witness data.
+    pub fn txid(&self) -> [u8; 32] {
+        self.txid
+    }
+
+    /// Hash of the transaction including witness data, equal to the txid if it has none.
+    pub fn wtxid(&self) -> [u8; 32] {
+        self.wtxid
+    }
+
+    /// BIP141 weight, see `GetTransactionWeight`.
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// Virtual size in vbytes, the weight divided by [`WITNESS_SCALE_FACTOR`] rounded up.
+    pub fn vsize(&self) -> u32 {
+        self.weight.div_ceil(WITNESS_SCALE_FACTOR)
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].previous_output.is_null()
+    }
+
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    /// The serialized transaction this was decoded from.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+unsafe fn copy_bytes(data: *const u8, len: u32) -> Vec<u8> {
+    if len == 0 {
+        return Vec::new();
+    }
+    core::slice::from_raw_parts(data, len as usize).to_vec()
+}
+
+unsafe fn read_input(handle: *const ffi::TransactionHandle, index: u32) -> TxIn {
+    let mut input = ffi::TransactionInputInfo::default();
+    ffi::transaction_input(handle, index, &mut input);
+    let witness = (0..input.witness_len)
+        .map(|item| {
+            let mut item_len = 0u32;
+            let data = ffi::transaction_input_witness(handle, index, item, &mut item_len);
+            copy_bytes(data, item_len)
+        })
+        .collect();
+    TxIn {
+        previous_output: OutPoint {
+            txid: input.prevout_hash,
+            vout: input.prevout_n,
+        },
+        script_sig: copy_bytes(input.script_sig, input.script_sig_len),
+        sequence: input.sequence,
+        witness,
+    }
+}
+
+unsafe fn read_output(handle: *const ffi::TransactionHandle, index: u32) -> TxOut {
+    let mut output = ffi::TransactionOutputInfo::default();
+    ffi::transaction_output(handle, index, &mut output);
+    TxOut {
+        value: output.value,
+        script_pubkey: copy_bytes(output.script_pubkey, output.script_pubkey_len),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::hex;
+
+    // coinbase of the genesis block
+    const GENESIS_COINBASE: [u8; 204] = hex!("01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000");
+
+    // two inputs, only the first one carrying a witness
+    const SEGWIT_TX: [u8; 145] = hex!("02000000000102000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f0100000000fdffffff202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f0000000003515253ffffffff0250c3000000000000160014ababababababababababababababababababababd204000000000000016a0203010203000000350c00");
+
+    fn le(mut hash: [u8; 32]) -> [u8; 32] {
+        hash.reverse();
+        hash
+    }
+
+    #[test]
+    fn test_legacy_transaction() {
+        let tx = Transaction::from_bytes(&GENESIS_COINBASE).unwrap();
+        assert_eq!(
+            tx.txid(),
+            le(hex!(
+                "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+            ))
+        );
+        assert_eq!(tx.wtxid(), tx.txid());
+        assert_eq!(tx.version(), 1);
+        assert_eq!(tx.lock_time(), 0);
+        assert!(tx.is_coinbase());
+        assert!(!tx.has_witness());
+        assert_eq!(tx.inputs().len(), 1);
+        assert_eq!(tx.inputs()[0].script_sig.len(), 77);
+        assert_eq!(tx.outputs().len(), 1);
+        assert_eq!(tx.outputs()[0].value, 50 * 100_000_000);
+        assert_eq!(tx.weight(), 204 * 4);
+        assert_eq!(tx.vsize(), 204);
+        assert_eq!(tx.as_bytes(), GENESIS_COINBASE);
+    }
+
+    #[test]
+    fn test_segwit_transaction() {
+        let tx = Transaction::from_bytes(&SEGWIT_TX).unwrap();
+        assert_eq!(
+            tx.txid(),
+            hex!("90df54397f87b28549a1f5e1004a6fefb0d76ad57e97bebaa1914f179af70c9e")
+        );
+        assert_eq!(
+            tx.wtxid(),
+            hex!("42d6f91bf9234a0731eb52a47d989591df635126a914d39de244925c80982727")
+        );
+        assert_eq!(tx.version(), 2);
+        assert_eq!(tx.lock_time(), 800_000);
+        assert!(!tx.is_coinbase());
+        assert!(tx.has_witness());
+
+        let inputs = tx.inputs();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].previous_output.vout, 1);
+        assert_eq!(inputs[0].sequence, 0xfffffffd);
+        assert!(inputs[0].script_sig.is_empty());
+        assert_eq!(inputs[0].witness, vec![vec![1, 2, 3], vec![]]);
+        assert_eq!(inputs[1].script_sig, vec![0x51, 0x52, 0x53]);
+        assert!(inputs[1].witness.is_empty());
+
+        let outputs = tx.outputs();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].value, 50_000);
+        assert_eq!(outputs[0].script_pubkey.len(), 22);
+        assert_eq!(outputs[1].script_pubkey, vec![0x6a]);
+
+        assert_eq!(tx.weight(), 553);
+        assert_eq!(tx.vsize(), 139);
+    }
+
+    #[test]
+    fn test_transaction_decode_errors() {
+        assert_eq!(
+            Transaction::from_bytes(&SEGWIT_TX[..100]),
+            Err(TransactionDecodeError::EndOfData)
+        );
+        assert_eq!(
+            Transaction::from_bytes(&[SEGWIT_TX.as_slice(), &[0]].concat()),
+            Err(TransactionDecodeError::TrailingData)
+        );
+
+        // segwit flag set but every witness is empty
+        let mut superfluous = SEGWIT_TX[..SEGWIT_TX.len() - 4 - 7].to_vec();
+        superfluous.extend_from_slice(&[0, 0]);
+        superfluous.extend_from_slice(&SEGWIT_TX[SEGWIT_TX.len() - 4..]);
+        assert_eq!(
+            Transaction::from_bytes(&superfluous),
+            Err(TransactionDecodeError::SuperfluousWitnessRecord)
+        );
+
+        let mut unknown_flag = SEGWIT_TX;
+        unknown_flag[5] = 2;
+        assert_eq!(
+            Transaction::from_bytes(&unknown_flag),
+            Err(TransactionDecodeError::UnknownOptionalData)
+        );
+
+        // one input encoded as a 3 byte compact size
+        let mut non_canonical = GENESIS_COINBASE[..4].to_vec();
+        non_canonical.extend_from_slice(&[0xfd, 0x01, 0x00]);
+        non_canonical.extend_from_slice(&GENESIS_COINBASE[5..]);
+        assert_eq!(
+            Transaction::from_bytes(&non_canonical),
+            Err(TransactionDecodeError::NonCanonicalCompactSize)
+        );
+
+        let mut too_large = GENESIS_COINBASE[..4].to_vec();
+        too_large.extend_from_slice(&[0xfe, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(
+            Transaction::from_bytes(&too_large),
+            Err(TransactionDecodeError::SizeTooLarge)
+        );
+    }
+
+    // The wrapper classifies failures by walking the encoding itself, so every
+    // place a check can fire is covered here
+    #[test]
+    fn test_transaction_decode_error_positions() {
+        let with_script_len =
+            |encoded: &[u8]| [&GENESIS_COINBASE[..41], encoded, &GENESIS_COINBASE[42..]].concat();
+        assert_eq!(
+            Transaction::from_bytes(&with_script_len(&[0xfe, 0x01, 0x00, 0x00, 0x02])),
+            Err(TransactionDecodeError::SizeTooLarge)
+        );
+        assert_eq!(
+            Transaction::from_bytes(&with_script_len(&[0xfe, 0x4d, 0x00, 0x00, 0x00])),
+            Err(TransactionDecodeError::NonCanonicalCompactSize)
+        );
+        assert_eq!(
+            Transaction::from_bytes(&with_script_len(&[0xff, 0x4d, 0, 0, 0, 0, 0, 0, 0])),
+            Err(TransactionDecodeError::NonCanonicalCompactSize)
+        );
+        assert_eq!(
+            Transaction::from_bytes(&with_script_len(&[0xfd, 0x00, 0x01])),
+            Err(TransactionDecodeError::EndOfData)
+        );
+
+        // inside the witness and the lock time of a segwit transaction
+        for len in [SEGWIT_TX.len() - 10, SEGWIT_TX.len() - 2] {
+            assert_eq!(
+                Transaction::from_bytes(&SEGWIT_TX[..len]),
+                Err(TransactionDecodeError::EndOfData)
+            );
+        }
+        // the marker with no flag byte after it
+        assert_eq!(
+            Transaction::from_bytes(&SEGWIT_TX[..5]),
+            Err(TransactionDecodeError::EndOfData)
+        );
+    }
+}
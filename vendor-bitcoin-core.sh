@@ -111,6 +111,12 @@ cat > "src/config/bitcoin-config.h" << 'EOL'
 
 EOL
 
+# Reapply this crate's changes to the vendored sources, stopping if one no longer applies
+for PATCH_FILE in "$CORE_VENDOR_GIT_ROOT"/src/native/patches/*.patch; do
+    echo "Applying $(basename "$PATCH_FILE")"
+    patch -p1 --forward --batch --no-backup-if-mismatch < "$PATCH_FILE"
+done
+
 # Remove .git directory for vendoring
 rm -rf .git/ || true
 